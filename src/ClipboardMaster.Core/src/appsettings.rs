@@ -0,0 +1,144 @@
+// 读取 appsettings.json
+//
+// appsettings.json 随程序发布，是设置的初始值：首次运行时与内置默认值合并后整体写入
+// 用户目录的 config.json。之后 config.json 保存完整的设置并优先生效，appsettings.json
+// 只为 config.json 中还没有的键（例如新版本增加的设置）提供值，修改已有键不会影响
+// 已经生成的 config.json。这里只读取核心使用的键，其余部分由界面程序读取。
+
+use std::path::PathBuf;
+
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::AppSettings;
+
+const FILE_NAME: &str = "appsettings.json";
+
+/// 查找 appsettings.json：先找程序所在目录，再找当前目录
+fn find() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(FILE_NAME)));
+    let current_dir = std::env::current_dir().ok().map(|dir| dir.join(FILE_NAME));
+    
+    [exe_dir, current_dir].into_iter().flatten().find(|path| path.is_file())
+}
+
+/// 读取 appsettings.json，文件不存在或无法解析时返回 `None`
+pub fn load() -> Option<Value> {
+    let path = find()?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            warn!("Failed to read {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    
+    match serde_json::from_str(&content) {
+        Ok(json) => Some(json),
+        Err(e) => {
+            warn!("Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// 将 appsettings.json 中核心使用的键写入设置，缺失或类型不符的键保持原值
+pub fn apply(settings: &mut AppSettings, json: &Value) {
+    set(json, "/Clipboard/IgnoreDuplicateInterval", &mut settings.ignore_duplicate_interval);
+    set(json, "/Clipboard/DuplicatePolicy", &mut settings.duplicate_policy);
+    // 规则的格式与 config.json 中的 auto_tag_rules 相同
+    set(json, "/Clipboard/AutoTagRules", &mut settings.auto_tag_rules);
+    
+    let capture_policy = &mut settings.capture_policy;
+    set(json, "/Clipboard/ExcludedApplications", &mut capture_policy.excluded_applications);
+    set(json, "/Clipboard/AppPolicies", &mut capture_policy.app_policies);
+    set(json, "/Clipboard/HonorConcealedHints", &mut capture_policy.honor_concealed_hints);
+    
+    let sensitive = &mut settings.sensitive;
+    set(json, "/Clipboard/FilterPatterns", &mut sensitive.keywords);
+    set(json, "/Security/MaskSensitiveData", &mut sensitive.enabled);
+    set(json, "/Security/SensitiveDataPolicy", &mut sensitive.policy);
+    set(json, "/Security/SensitiveDataExpireSeconds", &mut sensitive.expire_after_secs);
+    set(json, "/Security/DetectHighEntropyStrings", &mut sensitive.detect_high_entropy);
+    
+    let search = &mut settings.search;
+    set(json, "/Search/SearchInTags", &mut search.search_in_tags);
+    set(json, "/Search/SearchInSource", &mut search.search_in_source);
+    set(json, "/Search/SearchInMetadata", &mut search.search_in_metadata);
+    set(json, "/Search/EnableFuzzySearch", &mut search.enable_fuzzy_search);
+    set(json, "/Search/FuzzyThreshold", &mut search.fuzzy_threshold);
+    set(json, "/Search/FrecencyHalfLifeHours", &mut search.frecency_half_life_hours);
+    set(json, "/Search/SaveSearchHistory", &mut search.save_search_history);
+    set(json, "/Search/MaxSearchHistory", &mut search.max_search_history);
+}
+
+/// 将 `overlay` 合并到 `base`，对象按键递归合并，其余值直接覆盖
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn set<T: DeserializeOwned>(json: &Value, pointer: &str, target: &mut T) {
+    let value = match json.pointer(pointer) {
+        Some(value) => value.clone(),
+        None => return,
+    };
+    
+    match serde_json::from_value(value) {
+        Ok(value) => *target = value,
+        Err(e) => warn!("Ignoring invalid appsettings value {}: {}", pointer, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClipboardCore, DuplicatePolicy};
+    
+    #[test]
+    fn apply_reads_known_keys_and_skips_invalid_values() {
+        let mut settings = ClipboardCore::default_settings();
+        let json = serde_json::json!({
+            "Clipboard": {
+                "IgnoreDuplicateInterval": 30,
+                "DuplicatePolicy": "MoveToTop",
+                "ExcludedApplications": ["KeePass"]
+            },
+            "Search": {
+                "SearchInTags": false,
+                "FuzzyThreshold": 0.5
+            }
+        });
+        apply(&mut settings, &json);
+        assert_eq!(settings.ignore_duplicate_interval, 30);
+        assert_eq!(settings.duplicate_policy, DuplicatePolicy::MoveToTop);
+        assert_eq!(settings.capture_policy.excluded_applications, vec!["KeePass".to_string()]);
+        assert!(!settings.search.search_in_tags);
+        assert!(settings.search.search_in_source);
+        assert_eq!(settings.search.fuzzy_threshold, 0.5);
+        
+        let json = serde_json::json!({ "Clipboard": { "IgnoreDuplicateInterval": "often" } });
+        apply(&mut settings, &json);
+        assert_eq!(settings.ignore_duplicate_interval, 30);
+    }
+    
+    #[test]
+    fn merge_keeps_existing_keys_and_adds_missing_ones() {
+        let mut base = serde_json::json!({ "a": 1, "nested": { "b": 2, "c": 3 } });
+        merge(&mut base, serde_json::json!({ "nested": { "b": 20 }, "d": 4 }));
+        assert_eq!(base, serde_json::json!({ "a": 1, "nested": { "b": 20, "c": 3 }, "d": 4 }));
+    }
+}
@@ -0,0 +1,93 @@
+// 内存剪贴板后端
+//
+// 不依赖任何系统 API，用于测试以及没有可用系统剪贴板的环境。
+
+use parking_lot::{Mutex, RwLock};
+use crossbeam_channel::{Receiver, Sender};
+
+use super::{ClipboardBackend, ClipboardFormat};
+use crate::ClipboardContent;
+
+#[derive(Default)]
+pub struct InMemoryBackend {
+    contents: RwLock<Vec<ClipboardContent>>,
+    source: RwLock<(Option<String>, Option<String>)>,
+    subscribers: Mutex<Vec<Sender<()>>>,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 模拟其他程序复制了一项内容
+    pub fn push(&self, content: ClipboardContent) {
+        self.push_all(vec![content]);
+    }
+    
+    /// 模拟其他程序同时复制了多种格式的内容
+    pub fn push_all(&self, contents: Vec<ClipboardContent>) {
+        *self.contents.write() = contents;
+        self.notify();
+    }
+    
+    /// 设置后续内容的来源程序和窗口
+    pub fn set_source(&self, app: Option<String>, window: Option<String>) {
+        *self.source.write() = (app, window);
+    }
+    
+    /// 当前剪贴板上的全部内容
+    pub fn contents(&self) -> Vec<ClipboardContent> {
+        self.contents.read().clone()
+    }
+    
+    pub fn clear(&self) {
+        self.contents.write().clear();
+        self.notify();
+    }
+    
+    fn notify(&self) {
+        self.subscribers.lock().retain(|tx| tx.send(()).is_ok());
+    }
+}
+
+impl ClipboardBackend for InMemoryBackend {
+    fn name(&self) -> &str {
+        "memory"
+    }
+    
+    fn available_formats(&self) -> Result<Vec<ClipboardFormat>, Box<dyn std::error::Error>> {
+        Ok(self.contents.read().iter().map(ClipboardFormat::of).collect())
+    }
+    
+    fn read(&self, format: &ClipboardFormat) -> Result<Option<ClipboardContent>, Box<dyn std::error::Error>> {
+        Ok(self.contents.read()
+            .iter()
+            .find(|content| ClipboardFormat::of(content) == *format)
+            .cloned())
+    }
+    
+    fn write(&self, contents: &[ClipboardContent]) -> Result<(), Box<dyn std::error::Error>> {
+        // 与系统剪贴板一致：自己的写入同样会触发变更通知
+        self.push_all(contents.to_vec());
+        Ok(())
+    }
+    
+    fn subscribe(&self) -> Result<Receiver<()>, Box<dyn std::error::Error>> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        self.subscribers.lock().push(tx);
+        Ok(rx)
+    }
+    
+    fn unsubscribe(&self) {
+        self.subscribers.lock().clear();
+    }
+    
+    fn source_app(&self) -> Option<String> {
+        self.source.read().0.clone()
+    }
+    
+    fn source_window(&self) -> Option<String> {
+        self.source.read().1.clone()
+    }
+}
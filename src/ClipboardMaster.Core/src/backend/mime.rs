@@ -0,0 +1,198 @@
+// MIME 类型与剪贴板内容之间的转换
+//
+// X11 的 target 与 Wayland 的 mime type 使用同一套名称，两个后端共用这里的映射。
+
+use std::path::{Path, PathBuf};
+
+use super::ClipboardFormat;
+use crate::{capture_policy, ClipboardContent, FileItem, ImageData, ImageFormat};
+
+pub const TEXT_PLAIN_UTF8: &str = "text/plain;charset=utf-8";
+pub const TEXT_PLAIN: &str = "text/plain";
+pub const UTF8_STRING: &str = "UTF8_STRING";
+pub const STRING: &str = "STRING";
+pub const TEXT_HTML: &str = "text/html";
+pub const TEXT_RTF: &str = "text/rtf";
+pub const IMAGE_PNG: &str = "image/png";
+pub const URI_LIST: &str = "text/uri-list";
+
+/// MIME 类型对应的格式，无关的 target（TARGETS、TIMESTAMP 等）返回 `None`
+pub fn format_for_mime(mime: &str) -> Option<ClipboardFormat> {
+    match mime {
+        TEXT_PLAIN_UTF8 | TEXT_PLAIN | UTF8_STRING | STRING | "TEXT" => Some(ClipboardFormat::Text),
+        TEXT_HTML => Some(ClipboardFormat::Html),
+        TEXT_RTF | "application/rtf" => Some(ClipboardFormat::RichText),
+        IMAGE_PNG => Some(ClipboardFormat::Image),
+        URI_LIST => Some(ClipboardFormat::Files),
+        // 其他文本编码和图片格式都是上面几种的重复表示
+        _ if mime.starts_with("text/plain") || mime.starts_with("image/") => None,
+        // 密码管理器的隐藏提示不是 MIME 格式的名称，需要单独保留
+        _ if capture_policy::is_hint_format(mime) => Some(ClipboardFormat::Custom(mime.to_string())),
+        _ if mime.contains('/') => Some(ClipboardFormat::Custom(mime.to_string())),
+        _ => None,
+    }
+}
+
+/// 读取某一格式时依次尝试的 MIME 类型
+pub fn mime_candidates(format: &ClipboardFormat) -> Vec<String> {
+    let candidates: &[&str] = match format {
+        ClipboardFormat::Text => &[TEXT_PLAIN_UTF8, UTF8_STRING, TEXT_PLAIN, STRING],
+        ClipboardFormat::Html => &[TEXT_HTML],
+        ClipboardFormat::RichText => &[TEXT_RTF, "application/rtf"],
+        ClipboardFormat::Image => &[IMAGE_PNG],
+        ClipboardFormat::Files => &[URI_LIST],
+        ClipboardFormat::Custom(name) => return vec![name.clone()],
+    };
+    
+    candidates.iter().map(|s| s.to_string()).collect()
+}
+
+/// 将某个 MIME 类型的原始数据解析为剪贴板内容
+pub fn decode(format: &ClipboardFormat, mime: &str, data: Vec<u8>) -> Option<ClipboardContent> {
+    match format {
+        ClipboardFormat::Text => {
+            let text = if mime == STRING {
+                // STRING 为 Latin-1 编码
+                data.iter().map(|&b| b as char).collect()
+            } else {
+                String::from_utf8_lossy(&data).to_string()
+            };
+            Some(ClipboardContent::Text(text.trim_end_matches('\0').to_string()))
+        }
+        ClipboardFormat::Html => Some(ClipboardContent::Html(decode_html(&data))),
+        ClipboardFormat::RichText => Some(ClipboardContent::RichText(
+            String::from_utf8_lossy(&data).trim_end_matches('\0').to_string()
+        )),
+        ClipboardFormat::Image => {
+            let (width, height) = image::io::Reader::new(std::io::Cursor::new(&data))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok()?;
+            
+            Some(ClipboardContent::Image(ImageData {
+                data,
+                width,
+                height,
+                format: ImageFormat::Png,
+                thumbnail: Vec::new(),
+            }))
+        }
+        ClipboardFormat::Files => {
+            let files: Vec<FileItem> = String::from_utf8_lossy(&data)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(uri_to_path)
+                .map(|path| FileItem::from_path(&path))
+                .collect();
+            
+            if files.is_empty() {
+                None
+            } else {
+                Some(ClipboardContent::FileList(files))
+            }
+        }
+        ClipboardFormat::Custom(name) => Some(ClipboardContent::Custom(name.clone(), data)),
+    }
+}
+
+/// 将剪贴板内容编码为可提供给其他程序的 (MIME 类型, 数据) 列表
+pub fn encode(content: &ClipboardContent) -> Vec<(String, Vec<u8>)> {
+    match content {
+        ClipboardContent::Text(text) => [TEXT_PLAIN_UTF8, UTF8_STRING, TEXT_PLAIN]
+            .iter()
+            .map(|mime| (mime.to_string(), text.as_bytes().to_vec()))
+            .collect(),
+        ClipboardContent::Html(html) => vec![(TEXT_HTML.to_string(), html.as_bytes().to_vec())],
+        ClipboardContent::RichText(rtf) => vec![(TEXT_RTF.to_string(), rtf.as_bytes().to_vec())],
+        ClipboardContent::Image(img) => match img.format {
+            ImageFormat::Png => vec![(IMAGE_PNG.to_string(), img.data.clone())],
+            _ => {
+                // 其他格式统一转换为 PNG 提供
+                let mut png_data = Vec::new();
+                match image::load_from_memory(&img.data).and_then(|decoded| decoded.write_to(
+                    &mut std::io::Cursor::new(&mut png_data),
+                    image::ImageFormat::Png,
+                )) {
+                    Ok(_) => vec![(IMAGE_PNG.to_string(), png_data)],
+                    Err(e) => {
+                        log::warn!("Failed to convert image to PNG: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+        },
+        ClipboardContent::FileList(files) => {
+            let list: String = files.iter()
+                .map(|f| format!("{}\r\n", path_to_uri(&f.path)))
+                .collect();
+            vec![(URI_LIST.to_string(), list.into_bytes())]
+        }
+        ClipboardContent::Custom(name, data) => vec![(name.clone(), data.clone())],
+    }
+}
+
+fn decode_html(data: &[u8]) -> String {
+    // Firefox 以带 BOM 的 UTF-16 提供 text/html
+    let text = if data.starts_with(&[0xFF, 0xFE]) {
+        let wide: Vec<u16> = data[2..].chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&wide)
+    } else {
+        String::from_utf8_lossy(data).to_string()
+    };
+    
+    text.trim_end_matches('\0').to_string()
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // 跳过主机名部分（通常为空或 localhost）
+    let path = &rest[rest.find('/')?..];
+    
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    
+    Some(PathBuf::from(String::from_utf8_lossy(&decoded).to_string()))
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &b in path.to_string_lossy().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", b));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn concealed_hints_are_kept_as_custom_formats() {
+        for hint in ["x-kde-passwordManagerHint", "org.nspasteboard.ConcealedType", "org.nspasteboard.TransientType"] {
+            assert_eq!(format_for_mime(hint), Some(ClipboardFormat::Custom(hint.to_string())));
+        }
+        assert_eq!(format_for_mime("SAVE_TARGETS"), None);
+        assert_eq!(format_for_mime("text/plain;charset=iso-8859-1"), None);
+    }
+}
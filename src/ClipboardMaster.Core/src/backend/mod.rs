@@ -0,0 +1,131 @@
+// 剪贴板后端抽象
+//
+// 监控器只通过 `ClipboardBackend` 与系统剪贴板交互，平台相关的实现
+// （Win32、X11、Wayland、内存等）放在各自的子模块中。
+
+use std::sync::Arc;
+use crossbeam_channel::Receiver;
+use serde::{Deserialize, Serialize};
+
+use crate::ClipboardContent;
+
+mod memory;
+#[cfg(windows)]
+mod win32;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod mime;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod wayland;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod x11;
+
+pub use memory::InMemoryBackend;
+#[cfg(windows)]
+pub use win32::Win32Backend;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use wayland::WaylandBackend;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub use self::x11::X11Backend;
+
+/// 本程序写回剪贴板时附带的标记格式，内容为条目 ID
+///
+/// Windows 上注册为私有剪贴板格式，X11/Wayland 上作为额外的 target 提供，
+/// 监控器据此识别自身的写入。
+pub const SELF_WRITE_FORMAT: &str = "application/x-clipboard-master-item";
+
+/// 与平台无关的剪贴板格式
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ClipboardFormat {
+    Text,
+    Html,
+    RichText,
+    Image,
+    Files,
+    Custom(String),
+}
+
+impl ClipboardFormat {
+    /// 内容对应的格式
+    pub fn of(content: &ClipboardContent) -> Self {
+        match content {
+            ClipboardContent::Text(_) => ClipboardFormat::Text,
+            ClipboardContent::Html(_) => ClipboardFormat::Html,
+            ClipboardContent::RichText(_) => ClipboardFormat::RichText,
+            ClipboardContent::Image(_) => ClipboardFormat::Image,
+            ClipboardContent::FileList(_) => ClipboardFormat::Files,
+            ClipboardContent::Custom(name, _) => ClipboardFormat::Custom(name.clone()),
+        }
+    }
+}
+
+/// 系统剪贴板的读写与变更通知
+pub trait ClipboardBackend: Send + Sync {
+    /// 后端名称，用于日志
+    fn name(&self) -> &str;
+    
+    /// 当前剪贴板上可用的格式
+    fn available_formats(&self) -> Result<Vec<ClipboardFormat>, Box<dyn std::error::Error>>;
+    
+    /// 读取指定格式的内容，格式不存在时返回 `None`
+    fn read(&self, format: &ClipboardFormat) -> Result<Option<ClipboardContent>, Box<dyn std::error::Error>>;
+    
+    /// 清空剪贴板并写入给定的全部内容
+    fn write(&self, contents: &[ClipboardContent]) -> Result<(), Box<dyn std::error::Error>>;
+    
+    /// 同 `write`，`source_url` 为 HTML 内容的来源页面，平台格式不能携带来源时忽略
+    fn write_with_source(
+        &self,
+        contents: &[ClipboardContent],
+        _source_url: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.write(contents)
+    }
+    
+    /// 订阅剪贴板变更，每次变更发送一个通知；接收端被丢弃后后端停止发送
+    fn subscribe(&self) -> Result<Receiver<()>, Box<dyn std::error::Error>>;
+    
+    /// 结束全部订阅，已订阅的接收端随后断开，后端的监听线程随之退出
+    fn unsubscribe(&self);
+    
+    /// 当前剪贴板内容的来源程序，无法得知时返回 `None`
+    fn source_app(&self) -> Option<String> {
+        None
+    }
+    
+    /// 当前剪贴板内容的来源窗口
+    fn source_window(&self) -> Option<String> {
+        None
+    }
+}
+
+/// 按当前平台选择默认后端
+pub fn default_backend() -> Result<Arc<dyn ClipboardBackend>, Box<dyn std::error::Error>> {
+    #[cfg(windows)]
+    {
+        Ok(Arc::new(Win32Backend::new()))
+    }
+    
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            match WaylandBackend::new() {
+                Ok(backend) => return Ok(Arc::new(backend)),
+                Err(e) => log::warn!("Failed to use Wayland data-control: {}", e),
+            }
+        }
+        
+        // XWayland 下同样可以回退到 X11
+        if std::env::var_os("DISPLAY").is_some() {
+            match X11Backend::new() {
+                Ok(backend) => return Ok(Arc::new(backend)),
+                Err(e) => log::warn!("Failed to connect to X11 display: {}", e),
+            }
+        }
+    }
+    
+    #[cfg(not(windows))]
+    {
+        log::warn!("No system clipboard backend for this platform, falling back to in-memory backend");
+        Ok(Arc::new(InMemoryBackend::new()))
+    }
+}
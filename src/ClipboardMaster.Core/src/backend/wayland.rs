@@ -0,0 +1,498 @@
+// Wayland 剪贴板后端
+//
+// 基于 ext-data-control-v1（优先）或 wlr-data-control-unstable-v1 协议，
+// 无需窗口焦点即可读取和设置选择。两个协议的接口一一对应，这里用枚举包装
+// 后共用同一套处理逻辑。事件由后台线程分发，读取在调用方线程通过管道进行，
+// 向其他程序提供数据时在单独的线程中写入管道。
+//
+// 协议不提供选择的来源，`source_app` 和 `source_window` 始终为 `None`，
+// 按程序的捕获策略在 Wayland 下不会生效。
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::fd::AsFd;
+use std::sync::Arc;
+use std::time::Duration;
+use crossbeam_channel::{Receiver, Sender};
+use parking_lot::Mutex;
+use log::{error, warn};
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1::{self, ExtDataControlDeviceV1},
+    ext_data_control_manager_v1::ExtDataControlManagerV1,
+    ext_data_control_offer_v1::{self, ExtDataControlOfferV1},
+    ext_data_control_source_v1::{self, ExtDataControlSourceV1},
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+    zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
+};
+
+use super::{mime, ClipboardBackend, ClipboardFormat};
+use crate::ClipboardContent;
+
+// 等待数据来源写完管道的最长时间
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 本进程设置选择时提供的 (mime 类型, 数据)，作为 source 的用户数据，
+// 被新的 source 取代后旧 source 仍只提供自己的内容
+type SourceData = Arc<Vec<(String, Vec<u8>)>>;
+
+enum Manager {
+    Ext(ExtDataControlManagerV1),
+    Wlr(ZwlrDataControlManagerV1),
+}
+
+enum Device {
+    Ext(ExtDataControlDeviceV1),
+    Wlr(ZwlrDataControlDeviceV1),
+}
+
+#[derive(Clone)]
+enum Offer {
+    Ext(ExtDataControlOfferV1),
+    Wlr(ZwlrDataControlOfferV1),
+}
+
+impl Offer {
+    fn receive(&self, mime_type: String, fd: std::os::fd::BorrowedFd<'_>) {
+        match self {
+            Offer::Ext(offer) => offer.receive(mime_type, fd),
+            Offer::Wlr(offer) => offer.receive(mime_type, fd),
+        }
+    }
+    
+    fn destroy(&self) {
+        match self {
+            Offer::Ext(offer) => offer.destroy(),
+            Offer::Wlr(offer) => offer.destroy(),
+        }
+    }
+}
+
+struct Shared {
+    conn: Connection,
+    qh: QueueHandle<State>,
+    manager: Manager,
+    device: Device,
+    // 当前选择的 offer 及其提供的 mime 类型
+    current: Mutex<Option<(Offer, Vec<String>)>>,
+    subscribers: Mutex<Vec<Sender<()>>>,
+}
+
+struct State {
+    shared: Arc<Shared>,
+    // 已宣告但尚未成为选择的 offer
+    pending: HashMap<ObjectId, (Offer, Vec<String>)>,
+}
+
+impl State {
+    fn offer_announced(&mut self, offer: Offer, id: ObjectId) {
+        self.pending.insert(id, (offer, Vec::new()));
+    }
+    
+    fn offer_mime(&mut self, id: ObjectId, mime_type: String) {
+        if let Some((_, mimes)) = self.pending.get_mut(&id) {
+            mimes.push(mime_type);
+        }
+    }
+    
+    fn selection(&mut self, id: Option<ObjectId>) {
+        let selected = id.and_then(|id| self.pending.remove(&id));
+        
+        // 其余未被选中的 offer 已经失效
+        for (_, (offer, _)) in self.pending.drain() {
+            offer.destroy();
+        }
+        
+        if let Some((previous, _)) = std::mem::replace(&mut *self.shared.current.lock(), selected) {
+            previous.destroy();
+        }
+        self.shared.subscribers.lock().retain(|tx| tx.send(()).is_ok());
+    }
+    
+    fn primary_selection(&mut self, id: Option<ObjectId>) {
+        if let Some((offer, _)) = id.and_then(|id| self.pending.remove(&id)) {
+            offer.destroy();
+        }
+    }
+}
+
+// 接收方读取缓慢或不读取时写入会阻塞，在单独的线程中写入以免阻塞事件分发
+fn send(source: &SourceData, mime_type: String, fd: std::os::fd::OwnedFd) {
+    let Some(index) = source.iter().position(|(mime, _)| *mime == mime_type) else {
+        return;
+    };
+    
+    let source = source.clone();
+    std::thread::spawn(move || {
+        let mut file = std::fs::File::from(fd);
+        if let Err(e) = file.write_all(&source[index].1) {
+            warn!("Failed to send clipboard data: {}", e);
+        }
+    });
+}
+
+pub struct WaylandBackend {
+    shared: Arc<Shared>,
+}
+
+impl WaylandBackend {
+    /// 连接 `WAYLAND_DISPLAY` 指定的合成器
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+        
+        let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=1, ())?;
+        
+        let manager = match globals.bind::<ExtDataControlManagerV1, _, _>(&qh, 1..=1, ()) {
+            Ok(manager) => Manager::Ext(manager),
+            Err(_) => Manager::Wlr(
+                globals.bind::<ZwlrDataControlManagerV1, _, _>(&qh, 1..=2, ())
+                    .map_err(|_| "合成器不支持 data-control 协议")?,
+            ),
+        };
+        
+        let device = match &manager {
+            Manager::Ext(manager) => Device::Ext(manager.get_data_device(&seat, &qh, ())),
+            Manager::Wlr(manager) => Device::Wlr(manager.get_data_device(&seat, &qh, ())),
+        };
+        
+        let shared = Arc::new(Shared {
+            conn,
+            qh,
+            manager,
+            device,
+            current: Mutex::new(None),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        
+        let mut state = State {
+            shared: shared.clone(),
+            pending: HashMap::new(),
+        };
+        
+        // 获取当前选择
+        queue.roundtrip(&mut state)?;
+        
+        std::thread::spawn(move || {
+            loop {
+                if let Err(e) = queue.blocking_dispatch(&mut state) {
+                    error!("Wayland event loop error: {}", e);
+                    break;
+                }
+            }
+        });
+        
+        Ok(Self { shared })
+    }
+    
+    fn receive(&self, mime_type: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let offer = match &*self.shared.current.lock() {
+            Some((offer, mimes)) if mimes.iter().any(|m| m == mime_type) => offer.clone(),
+            _ => return Ok(None),
+        };
+        
+        let (mut reader, writer) = std::io::pipe()?;
+        offer.receive(mime_type.to_string(), writer.as_fd());
+        drop(writer);
+        self.shared.conn.flush()?;
+        
+        // 数据来源可能迟迟不关闭管道，在单独线程中读取以便超时返回
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        std::thread::spawn(move || {
+            let mut data = Vec::new();
+            let _ = tx.send(reader.read_to_end(&mut data).map(|_| data));
+        });
+        
+        match rx.recv_timeout(RECEIVE_TIMEOUT) {
+            Ok(data) => Ok(Some(data?)),
+            Err(_) => Err("读取剪贴板数据超时".into()),
+        }
+    }
+}
+
+impl ClipboardBackend for WaylandBackend {
+    fn name(&self) -> &str {
+        match self.shared.manager {
+            Manager::Ext(_) => "wayland-ext",
+            Manager::Wlr(_) => "wayland-wlr",
+        }
+    }
+    
+    fn available_formats(&self) -> Result<Vec<ClipboardFormat>, Box<dyn std::error::Error>> {
+        let mut formats = Vec::new();
+        if let Some((_, mimes)) = &*self.shared.current.lock() {
+            for mime_type in mimes {
+                if let Some(format) = mime::format_for_mime(mime_type) {
+                    if !formats.contains(&format) {
+                        formats.push(format);
+                    }
+                }
+            }
+        }
+        Ok(formats)
+    }
+    
+    fn read(&self, format: &ClipboardFormat) -> Result<Option<ClipboardContent>, Box<dyn std::error::Error>> {
+        for candidate in mime::mime_candidates(format) {
+            if let Some(data) = self.receive(&candidate)? {
+                return Ok(mime::decode(format, &candidate, data));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    fn write(&self, contents: &[ClipboardContent]) -> Result<(), Box<dyn std::error::Error>> {
+        let owned: SourceData = Arc::new(contents.iter().flat_map(mime::encode).collect());
+        let shared = &self.shared;
+        
+        match (&shared.manager, &shared.device) {
+            (Manager::Ext(manager), Device::Ext(device)) => {
+                let source = manager.create_data_source(&shared.qh, owned.clone());
+                for (mime_type, _) in owned.iter() {
+                    source.offer(mime_type.clone());
+                }
+                device.set_selection(Some(&source));
+            }
+            (Manager::Wlr(manager), Device::Wlr(device)) => {
+                let source = manager.create_data_source(&shared.qh, owned.clone());
+                for (mime_type, _) in owned.iter() {
+                    source.offer(mime_type.clone());
+                }
+                device.set_selection(Some(&source));
+            }
+            _ => unreachable!(),
+        }
+        
+        shared.conn.flush()?;
+        Ok(())
+    }
+    
+    fn subscribe(&self) -> Result<Receiver<()>, Box<dyn std::error::Error>> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        self.shared.subscribers.lock().push(tx);
+        Ok(rx)
+    }
+    
+    fn unsubscribe(&self) {
+        self.shared.subscribers.lock().clear();
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtDataControlManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ExtDataControlManagerV1,
+        _: <ExtDataControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlManagerV1,
+        _: <ZwlrDataControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ExtDataControlDeviceV1,
+        event: ext_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_data_control_device_v1::Event::DataOffer { id } => {
+                let object_id = id.id();
+                state.offer_announced(Offer::Ext(id), object_id);
+            }
+            ext_data_control_device_v1::Event::Selection { id } => {
+                state.selection(id.map(|offer| offer.id()));
+            }
+            ext_data_control_device_v1::Event::PrimarySelection { id } => {
+                state.primary_selection(id.map(|offer| offer.id()));
+            }
+            ext_data_control_device_v1::Event::Finished => {
+                warn!("Wayland data-control device finished");
+            }
+            _ => {}
+        }
+    }
+    
+    event_created_child!(State, ExtDataControlDeviceV1, [
+        ext_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ExtDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::DataOffer { id } => {
+                let object_id = id.id();
+                state.offer_announced(Offer::Wlr(id), object_id);
+            }
+            zwlr_data_control_device_v1::Event::Selection { id } => {
+                state.selection(id.map(|offer| offer.id()));
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id } => {
+                state.primary_selection(id.map(|offer| offer.id()));
+            }
+            zwlr_data_control_device_v1::Event::Finished => {
+                warn!("Wayland data-control device finished");
+            }
+            _ => {}
+        }
+    }
+    
+    event_created_child!(State, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &ExtDataControlOfferV1,
+        event: ext_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let ext_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state.offer_mime(offer.id(), mime_type);
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        offer: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            state.offer_mime(offer.id(), mime_type);
+        }
+    }
+}
+
+impl Dispatch<ExtDataControlSourceV1, SourceData> for State {
+    fn event(
+        _: &mut Self,
+        source: &ExtDataControlSourceV1,
+        event: ext_data_control_source_v1::Event,
+        data: &SourceData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_data_control_source_v1::Event::Send { mime_type, fd } => send(data, mime_type, fd),
+            ext_data_control_source_v1::Event::Cancelled => source.destroy(),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, SourceData> for State {
+    fn event(
+        _: &mut Self,
+        source: &ZwlrDataControlSourceV1,
+        event: zwlr_data_control_source_v1::Event,
+        data: &SourceData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { mime_type, fd } => send(data, mime_type, fd),
+            zwlr_data_control_source_v1::Event::Cancelled => source.destroy(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn read_text(backend: &WaylandBackend) -> Option<String> {
+        match backend.read(&ClipboardFormat::Text).unwrap() {
+            Some(ClipboardContent::Text(text)) => Some(text),
+            _ => None,
+        }
+    }
+    
+    // 需要支持 data-control 的合成器，如 `WLR_BACKENDS=headless sway`
+    #[test]
+    #[ignore = "需要 Wayland 合成器"]
+    fn newer_selection_replaces_the_previous_source() {
+        let owner = WaylandBackend::new().unwrap();
+        let reader = WaylandBackend::new().unwrap();
+        let changes = reader.subscribe().unwrap();
+        
+        owner.write(&[
+            ClipboardContent::Text("first".to_string()),
+            ClipboardContent::Custom("application/x-clipboard-master-test".to_string(), vec![1, 2, 3]),
+        ]).unwrap();
+        changes.recv_timeout(RECEIVE_TIMEOUT).unwrap();
+        assert_eq!(read_text(&reader).as_deref(), Some("first"));
+        
+        // 新的选择只提供自己的格式和内容
+        owner.write(&[ClipboardContent::Text("second".to_string())]).unwrap();
+        changes.recv_timeout(RECEIVE_TIMEOUT).unwrap();
+        assert_eq!(reader.available_formats().unwrap(), [ClipboardFormat::Text]);
+        assert_eq!(read_text(&reader).as_deref(), Some("second"));
+    }
+}
@@ -0,0 +1,567 @@
+// Win32 剪贴板后端
+//
+// 通过隐藏窗口的 AddClipboardFormatListener 接收 WM_CLIPBOARDUPDATE，
+// 读写使用标准剪贴板格式及注册格式。每次订阅有一个阻塞在 GetMessageW 上的监听线程，
+// 取消订阅时向其发送 WM_QUIT。
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use crossbeam_channel::{Receiver, Sender};
+use parking_lot::Mutex;
+use windows::core::{w, PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, GlobalFree, HANDLE, HGLOBAL, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::DataExchange::*;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Memory::*;
+use windows::Win32::System::Ole::{CF_DIB, CF_HDROP, CF_UNICODETEXT};
+use windows::Win32::System::Threading::{
+    GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Shell::{DragQueryFileW, HDROP};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use super::{ClipboardBackend, ClipboardFormat};
+use crate::{ClipboardContent, FileItem, ImageData, ImageFormat};
+
+const HTML_FORMAT: &str = "HTML Format";
+const RTF_FORMAT: &str = "Rich Text Format";
+
+/// BITMAPINFOHEADER 的大小
+const BITMAPINFOHEADER_SIZE: usize = 40;
+const BI_BITFIELDS: u32 = 3;
+
+pub struct Win32Backend {
+    /// 仍在运行的监听线程的线程 ID
+    listeners: Arc<Mutex<Vec<u32>>>,
+}
+
+impl Win32Backend {
+    pub fn new() -> Self {
+        Self {
+            listeners: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    
+    fn register_format(format_name: &str) -> Result<u32, Box<dyn std::error::Error>> {
+        unsafe {
+            let name_wide: Vec<u16> = format_name.encode_utf16().chain(std::iter::once(0)).collect();
+            let format = RegisterClipboardFormatW(PCWSTR(name_wide.as_ptr()));
+            if format == 0 {
+                return Err(format!("无法注册剪贴板格式: {}", format_name).into());
+            }
+            Ok(format)
+        }
+    }
+    
+    fn format_id(format: &ClipboardFormat) -> Result<u32, Box<dyn std::error::Error>> {
+        match format {
+            ClipboardFormat::Text => Ok(CF_UNICODETEXT.0 as u32),
+            ClipboardFormat::Image => Ok(CF_DIB.0 as u32),
+            ClipboardFormat::Files => Ok(CF_HDROP.0 as u32),
+            ClipboardFormat::Html => Self::register_format(HTML_FORMAT),
+            ClipboardFormat::RichText => Self::register_format(RTF_FORMAT),
+            ClipboardFormat::Custom(name) => Self::register_format(name),
+        }
+    }
+    
+    fn format_name(format: u32) -> Option<String> {
+        unsafe {
+            let mut name: [u16; 256] = [0; 256];
+            let len = GetClipboardFormatNameW(format, &mut name);
+            if len > 0 {
+                Some(String::from_utf16_lossy(&name[..len as usize]))
+            } else {
+                None
+            }
+        }
+    }
+    
+    /// 创建隐藏窗口并处理消息，开始监听后通过 `ready` 通知调用方
+    fn listen(
+        tx: Sender<()>,
+        ready: &Sender<Result<(), String>>,
+        listeners: &Mutex<Vec<u32>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // 创建隐藏窗口
+        let hwnd = unsafe {
+            let instance = GetModuleHandleW(None)?;
+            let class_name = w!("ClipboardMasterWindow");
+            
+            let wc = WNDCLASSW {
+                lpfnWndProc: Some(Self::window_proc),
+                hInstance: instance,
+                lpszClassName: class_name,
+                ..Default::default()
+            };
+            
+            RegisterClassW(&wc);
+            
+            CreateWindowExW(
+                Default::default(),
+                class_name,
+                w!("Clipboard Master"),
+                WS_OVERLAPPEDWINDOW,
+                0, 0, 0, 0,
+                HWND_MESSAGE,
+                None,
+                instance,
+                None,
+            )
+        };
+        
+        // 注册剪贴板监听
+        unsafe {
+            if !AddClipboardFormatListener(hwnd).as_bool() {
+                DestroyWindow(hwnd);
+                return Err("无法注册剪贴板监听".into());
+            }
+        }
+        
+        // 窗口创建后线程已有消息队列，此后发送的 WM_QUIT 不会丢失
+        let thread_id = unsafe { GetCurrentThreadId() };
+        listeners.lock().push(thread_id);
+        let _ = ready.send(Ok(()));
+        
+        // 消息循环，收到 WM_QUIT 或接收端关闭后退出。WM_QUIT 是线程消息，
+        // 不能按窗口过滤；GetMessageW 返回 0 表示 WM_QUIT，-1 表示出错
+        let mut msg = MSG::default();
+        loop {
+            unsafe {
+                if matches!(GetMessageW(&mut msg, HWND(0), 0, 0).0, 0 | -1) {
+                    break;
+                }
+                if msg.message == WM_CLIPBOARDUPDATE && tx.send(()).is_err() {
+                    break;
+                }
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        
+        // 清理，先移出列表，之后不会再有 WM_QUIT 发给这个线程 ID
+        listeners.lock().retain(|id| *id != thread_id);
+        unsafe {
+            RemoveClipboardFormatListener(hwnd);
+            DestroyWindow(hwnd);
+        }
+        
+        Ok(())
+    }
+    
+    extern "system" fn window_proc(
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> LRESULT {
+        unsafe {
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+    }
+    
+    /// 打开剪贴板，返回的守卫在释放时关闭剪贴板
+    fn open() -> Result<ClipboardGuard, Box<dyn std::error::Error>> {
+        unsafe {
+            // 其他程序可能短暂占用剪贴板，稍作重试
+            for _ in 0..10 {
+                if OpenClipboard(None).as_bool() {
+                    return Ok(ClipboardGuard);
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+        Err("无法打开剪贴板".into())
+    }
+    
+    fn read_global(format: u32) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        unsafe {
+            let handle = match GetClipboardData(format) {
+                Ok(handle) if !handle.is_invalid() => handle,
+                _ => return Ok(None),
+            };
+            let h_mem = HGLOBAL(handle.0);
+            let ptr = GlobalLock(h_mem) as *const u8;
+            if ptr.is_null() {
+                return Ok(None);
+            }
+            let size = GlobalSize(h_mem);
+            let data = std::slice::from_raw_parts(ptr, size).to_vec();
+            GlobalUnlock(h_mem);
+            Ok(Some(data))
+        }
+    }
+    
+    fn write_global(format: u32, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        unsafe {
+            let h_mem = GlobalAlloc(GMEM_MOVEABLE, data.len())?;
+            let ptr = GlobalLock(h_mem) as *mut u8;
+            if ptr.is_null() {
+                let _ = GlobalFree(h_mem);
+                return Err("无法锁定全局内存".into());
+            }
+            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+            GlobalUnlock(h_mem);
+            
+            // 设置成功后内存归系统所有，失败时仍需自行释放
+            if let Err(e) = SetClipboardData(format, HANDLE(h_mem.0)) {
+                let _ = GlobalFree(h_mem);
+                return Err(e.into());
+            }
+            Ok(())
+        }
+    }
+    
+    // 剪贴板所有者窗口，没有所有者时（部分程序以空窗口设置剪贴板）使用前台窗口
+    fn owner_window() -> HWND {
+        unsafe {
+            let owner = GetClipboardOwner();
+            if owner.0 != 0 {
+                owner
+            } else {
+                GetForegroundWindow()
+            }
+        }
+    }
+    
+    fn process_id(hwnd: HWND) -> u32 {
+        let mut pid = 0;
+        unsafe {
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        }
+        pid
+    }
+    
+    // 进程的可执行文件名，不含扩展名
+    fn process_name(pid: u32) -> Option<String> {
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut path: [u16; 1024] = [0; 1024];
+            let mut len = path.len() as u32;
+            let ok = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(path.as_mut_ptr()), &mut len);
+            let _ = CloseHandle(process);
+            
+            if !ok.as_bool() {
+                return None;
+            }
+            
+            PathBuf::from(String::from_utf16_lossy(&path[..len as usize]))
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+        }
+    }
+    
+    fn read_text() -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let data = match Self::read_global(CF_UNICODETEXT.0 as u32)? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        
+        let wide: Vec<u16> = data.chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect();
+        
+        Ok(Some(String::from_utf16_lossy(&wide)))
+    }
+    
+    fn read_image() -> Result<Option<ImageData>, Box<dyn std::error::Error>> {
+        let dib = match Self::read_global(CF_DIB.0 as u32)? {
+            Some(dib) => dib,
+            None => return Ok(None),
+        };
+        
+        // 头部不完整的数据无法解码
+        if dib.len() < BITMAPINFOHEADER_SIZE {
+            return Ok(None);
+        }
+        
+        let u16_at = |offset: usize| u16::from_le_bytes([dib[offset], dib[offset + 1]]);
+        let u32_at = |offset: usize| u32::from_le_bytes([dib[offset], dib[offset + 1], dib[offset + 2], dib[offset + 3]]);
+        let header_size = u32_at(0);
+        let bit_count = u16_at(14);
+        let compression = u32_at(16);
+        let colors_used = u32_at(32);
+        
+        // 像素数据之前还有颜色掩码（只有 BITMAPINFOHEADER 把掩码放在头部之后）和调色板
+        let masks_size = if compression == BI_BITFIELDS && header_size as usize == BITMAPINFOHEADER_SIZE { 3 * 4 } else { 0 };
+        let palette_size = match colors_used {
+            0 if (1..=8).contains(&bit_count) => (1u32 << bit_count) * 4,
+            0 => 0,
+            count => count.saturating_mul(4),
+        };
+        let pixels_offset = 14u32.saturating_add(header_size).saturating_add(masks_size).saturating_add(palette_size);
+        
+        // CF_DIB 是不带文件头的 BMP，补上 BITMAPFILEHEADER 后交给 image 解码
+        let mut bmp = Vec::with_capacity(dib.len() + 14);
+        bmp.extend_from_slice(b"BM");
+        bmp.extend_from_slice(&((dib.len() + 14) as u32).to_le_bytes());
+        bmp.extend_from_slice(&[0, 0, 0, 0]);
+        bmp.extend_from_slice(&pixels_offset.to_le_bytes());
+        bmp.extend_from_slice(&dib);
+        
+        let img = image::load_from_memory_with_format(&bmp, image::ImageFormat::Bmp)?;
+        
+        // 转换为PNG
+        let mut png_data = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut png_data),
+            image::ImageFormat::Png,
+        )?;
+        
+        Ok(Some(ImageData {
+            data: png_data,
+            width: img.width(),
+            height: img.height(),
+            format: ImageFormat::Png,
+            thumbnail: Vec::new(),
+        }))
+    }
+    
+    fn read_files() -> Result<Option<Vec<FileItem>>, Box<dyn std::error::Error>> {
+        unsafe {
+            let handle = match GetClipboardData(CF_HDROP.0 as u32) {
+                Ok(handle) if !handle.is_invalid() => handle,
+                _ => return Ok(None),
+            };
+            let hdrop = HDROP(handle.0);
+            
+            let count = DragQueryFileW(hdrop, u32::MAX, None);
+            let mut files = Vec::with_capacity(count as usize);
+            
+            for i in 0..count {
+                let len = DragQueryFileW(hdrop, i, None) as usize;
+                let mut buf = vec![0u16; len + 1];
+                DragQueryFileW(hdrop, i, Some(&mut buf));
+                let path = PathBuf::from(String::from_utf16_lossy(&buf[..len]));
+                files.push(FileItem::from_path(&path));
+            }
+            
+            Ok(Some(files))
+        }
+    }
+    
+    fn encode_image(img: &ImageData) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let decoded = image::load_from_memory(&img.data)?;
+        
+        let mut bmp = Vec::new();
+        decoded.write_to(
+            &mut std::io::Cursor::new(&mut bmp),
+            image::ImageFormat::Bmp,
+        )?;
+        
+        // 去掉 BITMAPFILEHEADER 即为 CF_DIB
+        Ok(bmp[14..].to_vec())
+    }
+    
+    fn encode_files(files: &[FileItem]) -> Vec<u8> {
+        // DROPFILES { pFiles, pt.x, pt.y, fNC, fWide } 后接双零结尾的宽字符路径列表
+        let mut data = Vec::new();
+        data.extend_from_slice(&20u32.to_le_bytes());
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&0i32.to_le_bytes());
+        data.extend_from_slice(&1i32.to_le_bytes());
+        
+        for file in files {
+            for c in file.path.to_string_lossy().encode_utf16().chain(std::iter::once(0)) {
+                data.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&0u16.to_le_bytes());
+        
+        data
+    }
+    
+    // 转换为剪贴板格式和对应的数据
+    fn encode(
+        content: &ClipboardContent,
+        source_url: Option<&str>,
+    ) -> Result<(u32, Vec<u8>), Box<dyn std::error::Error>> {
+        let format = Self::format_id(&ClipboardFormat::of(content))?;
+        let data = match content {
+            ClipboardContent::Text(text) => text.encode_utf16()
+                .chain(std::iter::once(0))
+                .flat_map(|c| c.to_le_bytes())
+                .collect(),
+            ClipboardContent::Html(html) => {
+                // 其他程序只识别带描述头的 CF_HTML
+                let payload = if crate::html::is_cf_html(html) {
+                    html.clone()
+                } else {
+                    crate::html::build_cf_html(html, source_url)
+                };
+                let mut data = payload.into_bytes();
+                data.push(0);
+                data
+            }
+            ClipboardContent::RichText(rtf) => {
+                let mut data = rtf.as_bytes().to_vec();
+                data.push(0);
+                data
+            }
+            ClipboardContent::Image(img) => Self::encode_image(img)?,
+            ClipboardContent::FileList(files) => Self::encode_files(files),
+            ClipboardContent::Custom(_, data) => data.clone(),
+        };
+        
+        Ok((format, data))
+    }
+}
+
+impl ClipboardBackend for Win32Backend {
+    fn name(&self) -> &str {
+        "win32"
+    }
+    
+    fn available_formats(&self) -> Result<Vec<ClipboardFormat>, Box<dyn std::error::Error>> {
+        let _guard = Self::open()?;
+        let html = Self::register_format(HTML_FORMAT)?;
+        let rtf = Self::register_format(RTF_FORMAT)?;
+        
+        let mut formats = Vec::new();
+        let mut format = 0;
+        loop {
+            format = unsafe { EnumClipboardFormats(format) };
+            if format == 0 {
+                break;
+            }
+            
+            let mapped = if format == CF_UNICODETEXT.0 as u32 {
+                Some(ClipboardFormat::Text)
+            } else if format == CF_DIB.0 as u32 {
+                Some(ClipboardFormat::Image)
+            } else if format == CF_HDROP.0 as u32 {
+                Some(ClipboardFormat::Files)
+            } else if format == html {
+                Some(ClipboardFormat::Html)
+            } else if format == rtf {
+                Some(ClipboardFormat::RichText)
+            } else {
+                // 标准格式没有名称，只保留注册格式
+                Self::format_name(format).map(ClipboardFormat::Custom)
+            };
+            
+            if let Some(mapped) = mapped {
+                if !formats.contains(&mapped) {
+                    formats.push(mapped);
+                }
+            }
+        }
+        
+        Ok(formats)
+    }
+    
+    fn read(&self, format: &ClipboardFormat) -> Result<Option<ClipboardContent>, Box<dyn std::error::Error>> {
+        let _guard = Self::open()?;
+        
+        let content = match format {
+            ClipboardFormat::Text => Self::read_text()?.map(ClipboardContent::Text),
+            ClipboardFormat::Image => Self::read_image()?.map(ClipboardContent::Image),
+            ClipboardFormat::Files => Self::read_files()?.map(ClipboardContent::FileList),
+            ClipboardFormat::Html => Self::read_global(Self::format_id(format)?)?
+                .map(|data| ClipboardContent::Html(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())),
+            ClipboardFormat::RichText => Self::read_global(Self::format_id(format)?)?
+                .map(|data| ClipboardContent::RichText(String::from_utf8_lossy(&data).trim_end_matches('\0').to_string())),
+            ClipboardFormat::Custom(name) => Self::read_global(Self::format_id(format)?)?
+                .map(|data| ClipboardContent::Custom(name.clone(), data)),
+        };
+        
+        Ok(content)
+    }
+    
+    fn write(&self, contents: &[ClipboardContent]) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_with_source(contents, None)
+    }
+    
+    fn write_with_source(
+        &self,
+        contents: &[ClipboardContent],
+        source_url: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // 先完成全部转换，转换失败时不会清空用户当前的剪贴板
+        let payloads = contents.iter()
+            .map(|content| Self::encode(content, source_url))
+            .collect::<Result<Vec<_>, _>>()?;
+        
+        let _guard = Self::open()?;
+        
+        unsafe {
+            if !EmptyClipboard().as_bool() {
+                return Err("无法清空剪贴板".into());
+            }
+        }
+        
+        for (format, data) in &payloads {
+            Self::write_global(*format, data)?;
+        }
+        
+        Ok(())
+    }
+    
+    fn subscribe(&self) -> Result<Receiver<()>, Box<dyn std::error::Error>> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let (ready_tx, ready_rx) = crossbeam_channel::bounded(1);
+        let listeners = self.listeners.clone();
+        
+        std::thread::spawn(move || {
+            if let Err(e) = Self::listen(tx, &ready_tx, &listeners) {
+                let _ = ready_tx.send(Err(e.to_string()));
+            }
+        });
+        
+        // 等待监听开始，创建窗口或注册监听失败时返回错误
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(rx),
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err("剪贴板监听线程已退出".into()),
+        }
+    }
+    
+    fn unsubscribe(&self) {
+        // 持有锁期间监听线程无法把自己移出列表，列表中的线程 ID 都仍然有效
+        let listeners = self.listeners.lock();
+        for thread_id in listeners.iter() {
+            unsafe {
+                PostThreadMessageW(*thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+        }
+    }
+    
+    /// 剪贴板所有者进程的程序名，如 `KeePass`
+    fn source_app(&self) -> Option<String> {
+        match Self::process_id(Self::owner_window()) {
+            0 => None,
+            pid => Self::process_name(pid),
+        }
+    }
+    
+    fn source_window(&self) -> Option<String> {
+        unsafe {
+            // OLE 剪贴板的所有者是隐藏的 CLIPBRDWNDCLASS 窗口，前台窗口属于同一进程时使用前台窗口的类名
+            let owner = Self::owner_window();
+            let foreground = GetForegroundWindow();
+            let hwnd = if Self::process_id(foreground) == Self::process_id(owner) { foreground } else { owner };
+            
+            let mut class_name: [u16; 256] = [0; 256];
+            let len = GetClassNameW(hwnd, &mut class_name);
+            
+            if len > 0 {
+                Some(String::from_utf16_lossy(&class_name[..len as usize]))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+struct ClipboardGuard;
+
+impl Drop for ClipboardGuard {
+    fn drop(&mut self) {
+        unsafe {
+            CloseClipboard();
+        }
+    }
+}
@@ -0,0 +1,522 @@
+// X11 剪贴板后端
+//
+// 通过 XFixes 的 SelectionNotify 监听 CLIPBOARD 所有权变化，读取时用
+// ConvertSelection 向当前所有者请求数据；写入时本进程成为所有者并响应
+// 其他程序的 SelectionRequest，超过单个请求大小的数据使用 INCR 分段发送。
+// 所有事件由一个后台线程统一分发。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crossbeam_channel::{Receiver, Sender};
+use parking_lot::{Mutex, RwLock};
+use log::{error, warn};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask,
+    PropMode, Property, PropertyNotifyEvent, SelectionNotifyEvent, SelectionRequestEvent, Window,
+    WindowClass, SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{CURRENT_TIME, NONE};
+
+use super::{mime, ClipboardBackend, ClipboardFormat};
+use crate::ClipboardContent;
+
+// 等待剪贴板所有者响应的最长时间
+const SELECTION_TIMEOUT: Duration = Duration::from_secs(2);
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        INCR,
+        _NET_WM_PID,
+        CLIPBOARD_MASTER_DATA,
+    }
+}
+
+// 向其他程序分段发送的数据
+struct IncrTransfer {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    data: Vec<u8>,
+    offset: usize,
+    // 最近一次收到对方删除属性的时间，超时未继续的传输被丢弃
+    last_activity: Instant,
+}
+
+struct Shared {
+    conn: RustConnection,
+    window: Window,
+    atoms: Atoms,
+    // 最近一次 CLIPBOARD 所有者窗口
+    owner: RwLock<Window>,
+    // 本进程持有剪贴板时提供的 (target, 数据)
+    owned: RwLock<Vec<(Atom, Vec<u8>)>>,
+    // 进行中的 INCR 发送
+    transfers: Mutex<Vec<IncrTransfer>>,
+    // 每次所有者变化加一，用于判断缓存的 TARGETS 是否过期
+    selection_serial: AtomicU64,
+    // (序号, 当前所有者提供的 target 名称)
+    targets_cache: Mutex<Option<(u64, Vec<String>)>>,
+    subscribers: Mutex<Vec<Sender<()>>>,
+    // 转发给读取方的 SelectionNotify / PropertyNotify 事件
+    replies_tx: Sender<Event>,
+    replies_rx: Receiver<Event>,
+    // 同一时间只进行一次 ConvertSelection
+    read_lock: Mutex<()>,
+}
+
+pub struct X11Backend {
+    shared: Arc<Shared>,
+}
+
+impl X11Backend {
+    /// 连接 `DISPLAY` 指定的 X 服务器
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let screen = &conn.setup().roots[screen_num];
+        
+        let version = conn.xfixes_query_version(5, 0)?.reply()?;
+        if version.major_version < 1 {
+            return Err("X 服务器不支持 XFixes 选择通知".into());
+        }
+        
+        // 创建隐藏窗口，用于接收选择数据和属性变化
+        let window = conn.generate_id()?;
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            0, 0, 1, 1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            screen.root_visual,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        
+        let atoms = Atoms::new(&conn)?.reply()?;
+        
+        conn.xfixes_select_selection_input(
+            window,
+            atoms.CLIPBOARD,
+            SelectionEventMask::SET_SELECTION_OWNER
+                | SelectionEventMask::SELECTION_WINDOW_DESTROY
+                | SelectionEventMask::SELECTION_CLIENT_CLOSE,
+        )?;
+        conn.flush()?;
+        
+        let (replies_tx, replies_rx) = crossbeam_channel::unbounded();
+        let shared = Arc::new(Shared {
+            conn,
+            window,
+            atoms,
+            owner: RwLock::new(NONE),
+            owned: RwLock::new(Vec::new()),
+            transfers: Mutex::new(Vec::new()),
+            selection_serial: AtomicU64::new(0),
+            targets_cache: Mutex::new(None),
+            subscribers: Mutex::new(Vec::new()),
+            replies_tx,
+            replies_rx,
+            read_lock: Mutex::new(()),
+        });
+        
+        let event_shared = shared.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = Self::event_loop(&event_shared) {
+                error!("X11 event loop error: {}", e);
+            }
+        });
+        
+        Ok(Self { shared })
+    }
+    
+    fn event_loop(shared: &Shared) -> Result<(), Box<dyn std::error::Error>> {
+        let atoms = &shared.atoms;
+        
+        loop {
+            match shared.conn.wait_for_event()? {
+                Event::XfixesSelectionNotify(e) if e.selection == atoms.CLIPBOARD => {
+                    *shared.owner.write() = e.owner;
+                    shared.selection_serial.fetch_add(1, Ordering::SeqCst);
+                    shared.subscribers.lock().retain(|tx| tx.send(()).is_ok());
+                }
+                Event::SelectionRequest(e) => {
+                    if let Err(e) = Self::handle_request(shared, e) {
+                        warn!("Failed to answer selection request: {}", e);
+                    }
+                }
+                Event::SelectionClear(e) if e.selection == atoms.CLIPBOARD => {
+                    shared.owned.write().clear();
+                }
+                event @ Event::SelectionNotify(_) => {
+                    let _ = shared.replies_tx.send(event);
+                }
+                Event::PropertyNotify(e)
+                    if e.window == shared.window
+                        && e.atom == atoms.CLIPBOARD_MASTER_DATA
+                        && e.state == Property::NEW_VALUE =>
+                {
+                    let _ = shared.replies_tx.send(Event::PropertyNotify(e));
+                }
+                Event::PropertyNotify(e) if e.state == Property::DELETE => {
+                    if let Err(e) = Self::continue_transfer(shared, e) {
+                        warn!("Failed to send clipboard data incrementally: {}", e);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    fn handle_request(shared: &Shared, request: SelectionRequestEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &shared.conn;
+        // 旧式客户端不指定属性，此时使用 target 作为属性名
+        let property = if request.property == NONE { request.target } else { request.property };
+        
+        let served = if request.selection != shared.atoms.CLIPBOARD {
+            false
+        } else if request.target == shared.atoms.TARGETS {
+            let mut targets: Vec<Atom> = shared.owned.read().iter().map(|(atom, _)| *atom).collect();
+            targets.push(shared.atoms.TARGETS);
+            conn.change_property32(PropMode::REPLACE, request.requestor, property, AtomEnum::ATOM, &targets)?;
+            true
+        } else if let Some((_, data)) = shared.owned.read().iter().find(|(atom, _)| *atom == request.target) {
+            if data.len() > Self::incr_chunk_size(conn) {
+                Self::start_transfer(shared, &request, property, data.clone())?;
+            } else {
+                conn.change_property8(PropMode::REPLACE, request.requestor, property, request.target, data)?;
+            }
+            true
+        } else {
+            false
+        };
+        
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: if served { property } else { NONE },
+        };
+        conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify)?;
+        conn.flush()?;
+        
+        Ok(())
+    }
+    
+    // 单次 ChangeProperty 可携带的数据量，超过时使用 INCR
+    fn incr_chunk_size(conn: &RustConnection) -> usize {
+        conn.maximum_request_bytes() / 4
+    }
+    
+    // 先在属性中写入 INCR 和总大小，对方每删除一次属性就发送下一段
+    fn start_transfer(
+        shared: &Shared,
+        request: &SelectionRequestEvent,
+        property: Atom,
+        data: Vec<u8>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = &shared.conn;
+        conn.change_window_attributes(
+            request.requestor,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        let size = u32::try_from(data.len()).unwrap_or(u32::MAX);
+        conn.change_property32(PropMode::REPLACE, request.requestor, property, shared.atoms.INCR, &[size])?;
+        
+        let mut transfers = shared.transfers.lock();
+        transfers.retain(|transfer| {
+            transfer.last_activity.elapsed() < SELECTION_TIMEOUT
+                && !(transfer.requestor == request.requestor && transfer.property == property)
+        });
+        transfers.push(IncrTransfer {
+            requestor: request.requestor,
+            property,
+            target: request.target,
+            data,
+            offset: 0,
+            last_activity: Instant::now(),
+        });
+        
+        Ok(())
+    }
+    
+    fn continue_transfer(shared: &Shared, event: PropertyNotifyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let mut transfers = shared.transfers.lock();
+        let index = match transfers.iter().position(|transfer| {
+            transfer.requestor == event.window && transfer.property == event.atom
+        }) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        
+        let conn = &shared.conn;
+        let transfer = &mut transfers[index];
+        let end = (transfer.offset + Self::incr_chunk_size(conn)).min(transfer.data.len());
+        let chunk = &transfer.data[transfer.offset..end];
+        conn.change_property8(PropMode::REPLACE, transfer.requestor, transfer.property, transfer.target, chunk)?;
+        conn.flush()?;
+        
+        // 写入空段表示结束
+        if chunk.is_empty() {
+            let transfer = transfers.remove(index);
+            conn.change_window_attributes(
+                transfer.requestor,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+            )?;
+            conn.flush()?;
+        } else {
+            transfer.offset = end;
+            transfer.last_activity = Instant::now();
+        }
+        
+        Ok(())
+    }
+    
+    fn intern(&self, name: &str) -> Result<Atom, Box<dyn std::error::Error>> {
+        Ok(self.shared.conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+    }
+    
+    fn atom_name(&self, atom: Atom) -> Result<String, Box<dyn std::error::Error>> {
+        let reply = self.shared.conn.get_atom_name(atom)?.reply()?;
+        Ok(String::from_utf8_lossy(&reply.name).to_string())
+    }
+    
+    /// 请求当前所有者将 CLIPBOARD 转换为指定 target，所有者拒绝时返回 `None`
+    fn convert_selection(&self, target: Atom) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let shared = &self.shared;
+        let conn = &shared.conn;
+        let property = shared.atoms.CLIPBOARD_MASTER_DATA;
+        let _lock = shared.read_lock.lock();
+        
+        // 丢弃上一次请求遗留的事件
+        while shared.replies_rx.try_recv().is_ok() {}
+        
+        conn.convert_selection(shared.window, shared.atoms.CLIPBOARD, target, property, CURRENT_TIME)?;
+        conn.flush()?;
+        
+        let deadline = Instant::now() + SELECTION_TIMEOUT;
+        loop {
+            match shared.replies_rx.recv_deadline(deadline) {
+                Ok(Event::SelectionNotify(e)) if e.target == target => {
+                    if e.property == NONE {
+                        return Ok(None);
+                    }
+                    break;
+                }
+                Ok(_) => continue,
+                Err(_) => return Err("等待剪贴板所有者响应超时".into()),
+            }
+        }
+        
+        let reply = conn.get_property(true, shared.window, property, AtomEnum::ANY, 0, u32::MAX)?.reply()?;
+        if reply.type_ != shared.atoms.INCR {
+            return Ok(Some(reply.value));
+        }
+        
+        // 大块数据使用 INCR 协议分段传输，收到空段表示结束
+        let mut data = Vec::new();
+        loop {
+            match shared.replies_rx.recv_deadline(Instant::now() + SELECTION_TIMEOUT) {
+                Ok(Event::PropertyNotify(_)) => {
+                    let chunk = conn.get_property(true, shared.window, property, AtomEnum::ANY, 0, u32::MAX)?.reply()?;
+                    if chunk.value.is_empty() {
+                        return Ok(Some(data));
+                    }
+                    data.extend_from_slice(&chunk.value);
+                }
+                Ok(_) => continue,
+                Err(_) => return Err("分段读取剪贴板超时".into()),
+            }
+        }
+    }
+    
+    /// 当前所有者提供的 target，同一次所有者变化内只查询一次
+    fn targets(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let serial = self.shared.selection_serial.load(Ordering::SeqCst);
+        if let Some((cached, names)) = &*self.shared.targets_cache.lock() {
+            if *cached == serial {
+                return Ok(names.clone());
+            }
+        }
+        
+        let names = match self.convert_selection(self.shared.atoms.TARGETS)? {
+            Some(data) => {
+                let mut names = Vec::new();
+                for chunk in data.chunks_exact(4) {
+                    let atom = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                    names.push(self.atom_name(atom)?);
+                }
+                names
+            }
+            None => Vec::new(),
+        };
+        
+        // 查询期间所有者又发生变化时，按查询开始时的序号保存，下次读取会重新查询
+        *self.shared.targets_cache.lock() = Some((serial, names.clone()));
+        Ok(names)
+    }
+    
+    fn owner_property(&self, property: Atom, type_: impl Into<Atom>) -> Option<Vec<u8>> {
+        let owner = *self.shared.owner.read();
+        if owner == NONE {
+            return None;
+        }
+        
+        let reply = self.shared.conn
+            .get_property(false, owner, property, type_, 0, 1024)
+            .ok()?
+            .reply()
+            .ok()?;
+        if reply.value.is_empty() {
+            None
+        } else {
+            Some(reply.value)
+        }
+    }
+}
+
+impl ClipboardBackend for X11Backend {
+    fn name(&self) -> &str {
+        "x11"
+    }
+    
+    fn available_formats(&self) -> Result<Vec<ClipboardFormat>, Box<dyn std::error::Error>> {
+        let mut formats = Vec::new();
+        for target in self.targets()? {
+            if let Some(format) = mime::format_for_mime(&target) {
+                if !formats.contains(&format) {
+                    formats.push(format);
+                }
+            }
+        }
+        Ok(formats)
+    }
+    
+    fn read(&self, format: &ClipboardFormat) -> Result<Option<ClipboardContent>, Box<dyn std::error::Error>> {
+        let targets = self.targets()?;
+        
+        for candidate in mime::mime_candidates(format) {
+            if !targets.contains(&candidate) {
+                continue;
+            }
+            
+            let atom = self.intern(&candidate)?;
+            if let Some(data) = self.convert_selection(atom)? {
+                return Ok(mime::decode(format, &candidate, data));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    fn write(&self, contents: &[ClipboardContent]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut owned = Vec::new();
+        for content in contents {
+            for (name, data) in mime::encode(content) {
+                owned.push((self.intern(&name)?, data));
+            }
+        }
+        
+        let shared = &self.shared;
+        *shared.owned.write() = owned;
+        shared.conn.set_selection_owner(shared.window, shared.atoms.CLIPBOARD, CURRENT_TIME)?;
+        shared.conn.flush()?;
+        
+        let owner = shared.conn.get_selection_owner(shared.atoms.CLIPBOARD)?.reply()?.owner;
+        if owner != shared.window {
+            return Err("无法获取剪贴板所有权".into());
+        }
+        
+        Ok(())
+    }
+    
+    fn subscribe(&self) -> Result<Receiver<()>, Box<dyn std::error::Error>> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        self.shared.subscribers.lock().push(tx);
+        Ok(rx)
+    }
+    
+    fn unsubscribe(&self) {
+        self.shared.subscribers.lock().clear();
+    }
+    
+    fn source_app(&self) -> Option<String> {
+        // 优先通过 _NET_WM_PID 取进程名，其次使用 WM_CLASS 的实例名
+        if let Some(pid) = self.owner_property(self.shared.atoms._NET_WM_PID, AtomEnum::CARDINAL) {
+            if pid.len() >= 4 {
+                let pid = u32::from_ne_bytes([pid[0], pid[1], pid[2], pid[3]]);
+                if let Ok(comm) = std::fs::read_to_string(format!("/proc/{}/comm", pid)) {
+                    return Some(comm.trim().to_string());
+                }
+            }
+        }
+        
+        let class = self.owner_property(AtomEnum::WM_CLASS.into(), AtomEnum::STRING)?;
+        class.split(|&b| b == 0)
+            .next()
+            .filter(|s| !s.is_empty())
+            .map(|s| String::from_utf8_lossy(s).to_string())
+    }
+    
+    fn source_window(&self) -> Option<String> {
+        let class = self.owner_property(AtomEnum::WM_CLASS.into(), AtomEnum::STRING)?;
+        class.split(|&b| b == 0)
+            .nth(1)
+            .filter(|s| !s.is_empty())
+            .map(|s| String::from_utf8_lossy(s).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // 两个连接分别作为所有者和读取方，需要 X 服务器：xvfb-run cargo test -- --ignored
+    #[test]
+    #[ignore = "需要 X 服务器"]
+    fn large_selections_are_sent_incrementally() {
+        let owner = X11Backend::new().unwrap();
+        let reader = X11Backend::new().unwrap();
+        let changes = reader.subscribe().unwrap();
+        
+        let name = "application/x-clipboard-master-test";
+        let large: Vec<u8> = (0..X11Backend::incr_chunk_size(&owner.shared.conn) * 3 + 1)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        owner.write(&[
+            ClipboardContent::Text("hello".to_string()),
+            ClipboardContent::Custom(name.to_string(), large.clone()),
+        ]).unwrap();
+        changes.recv_timeout(SELECTION_TIMEOUT).unwrap();
+        
+        let formats = reader.available_formats().unwrap();
+        assert!(formats.contains(&ClipboardFormat::Text));
+        assert!(formats.contains(&ClipboardFormat::Custom(name.to_string())));
+        assert!(matches!(
+            reader.read(&ClipboardFormat::Text).unwrap(),
+            Some(ClipboardContent::Text(text)) if text == "hello"
+        ));
+        assert!(matches!(
+            reader.read(&ClipboardFormat::Custom(name.to_string())).unwrap(),
+            Some(ClipboardContent::Custom(_, data)) if data == large
+        ));
+        
+        // 所有者未变化时沿用缓存的 TARGETS
+        let serial = reader.shared.selection_serial.load(Ordering::SeqCst);
+        assert!(matches!(&*reader.shared.targets_cache.lock(), Some((cached, _)) if *cached == serial));
+        
+        owner.write(&[ClipboardContent::Text("next".to_string())]).unwrap();
+        changes.recv_timeout(SELECTION_TIMEOUT).unwrap();
+        assert_eq!(reader.available_formats().unwrap(), [ClipboardFormat::Text]);
+    }
+}
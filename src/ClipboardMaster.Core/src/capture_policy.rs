@@ -0,0 +1,367 @@
+// 按来源程序的捕获策略
+//
+// 排除的程序（如密码管理器）复制的内容不会保存；其他程序可以按名称或窗口类
+// 指定只保存文本、不保存图片或到期自动删除。密码管理器在剪贴板上附带的
+// “隐藏”提示格式同样会被遵守。
+
+use serde::{Deserialize, Serialize};
+
+use crate::{regexp, ClipboardFormat};
+
+/// 只要存在就表示内容不应记录的格式
+const CONCEALED_FORMATS: &[&str] = &[
+    // Windows
+    "ExcludeClipboardContentFromMonitorProcessing",
+    "Clipboard Viewer Ignore",
+    // nspasteboard.org 的约定，部分跨平台程序在其他平台上也会设置
+    "org.nspasteboard.ConcealedType",
+    "org.nspasteboard.TransientType",
+];
+
+/// 值为 0 时表示内容不应进入剪贴板历史（Windows）
+const CAN_INCLUDE_IN_HISTORY_FORMAT: &str = "CanIncludeInClipboardHistory";
+/// 值为 `secret` 时表示内容为密码（KDE）
+const KDE_PASSWORD_HINT_FORMAT: &str = "x-kde-passwordManagerHint";
+
+/// OLE 剪贴板的私有数据，只在复制它的进程中有效，不保存也不写回
+const OLE_PRIVATE_FORMATS: &[&str] = &["DataObject", "Ole Private Data"];
+
+/// 名称的匹配方式，均不区分大小写
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NamePattern {
+    /// 完全相同
+    Exact(String),
+    /// 通配符，`*` 匹配任意字符，`?` 匹配单个字符
+    Glob(String),
+    /// 正则表达式，匹配名称的任意部分
+    Regex(String),
+}
+
+impl NamePattern {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Exact(pattern) => pattern.to_lowercase() == name.to_lowercase(),
+            NamePattern::Glob(_) | NamePattern::Regex(_) => match self.to_regex() {
+                Some(pattern) => regexp::compile(&pattern).is_ok_and(|regex| regex.is_match(name.as_bytes())),
+                None => false,
+            },
+        }
+    }
+    
+    fn validate(&self) -> Result<(), String> {
+        let pattern = match self {
+            NamePattern::Exact(pattern) | NamePattern::Glob(pattern) | NamePattern::Regex(pattern) => pattern,
+        };
+        if pattern.trim().is_empty() {
+            return Err("程序名称不能为空".to_string());
+        }
+        
+        match self.to_regex() {
+            Some(regex) => regexp::compile(&regex).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+    
+    fn to_regex(&self) -> Option<String> {
+        match self {
+            NamePattern::Exact(_) => None,
+            NamePattern::Glob(glob) => {
+                let mut regex = String::from("(?i)^");
+                for c in glob.chars() {
+                    match c {
+                        '*' => regex.push_str(".*"),
+                        '?' => regex.push('.'),
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex.push('$');
+                Some(regex)
+            }
+            NamePattern::Regex(pattern) => Some(format!("(?i){}", pattern)),
+        }
+    }
+}
+
+/// 对匹配程序的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureAction {
+    /// 不保存
+    Ignore,
+    /// 只保存纯文本
+    TextOnly,
+    /// 不保存图片
+    NoImages,
+    /// 保存，指定秒数后自动删除
+    Expire(u32),
+}
+
+impl CaptureAction {
+    /// 是否读取该格式
+    pub fn allows(&self, format: &ClipboardFormat) -> bool {
+        match self {
+            CaptureAction::Ignore => false,
+            CaptureAction::TextOnly => *format == ClipboardFormat::Text,
+            CaptureAction::NoImages => *format != ClipboardFormat::Image,
+            CaptureAction::Expire(_) => true,
+        }
+    }
+}
+
+/// 按来源程序的捕获策略，`app` 和 `window_class` 都设置时需要同时满足
+///
+/// 来源取决于后端：Windows 为剪贴板所有者的程序名（不含 `.exe`）和窗口类，X11 为进程名和
+/// WM_CLASS。Wayland 的 data-control 协议不提供来源，指定了程序或窗口类的策略在 Wayland
+/// 下不会匹配，只有隐藏提示仍然有效。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppPolicy {
+    #[serde(default)]
+    pub app: Option<NamePattern>,
+    #[serde(default)]
+    pub window_class: Option<NamePattern>,
+    pub action: CaptureAction,
+}
+
+impl AppPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.app.is_none() && self.window_class.is_none() {
+            return Err("捕获策略需要指定程序或窗口类".to_string());
+        }
+        if self.action == CaptureAction::Expire(0) {
+            return Err("自动删除的时间必须大于 0".to_string());
+        }
+        
+        for pattern in [&self.app, &self.window_class].into_iter().flatten() {
+            pattern.validate()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 来源是否符合策略，来源未知时不匹配
+    pub fn matches(&self, app: Option<&str>, window_class: Option<&str>) -> bool {
+        let matches = |pattern: &Option<NamePattern>, name: Option<&str>| match (pattern, name) {
+            (None, _) => true,
+            (Some(pattern), Some(name)) => pattern.matches(name),
+            (Some(_), None) => false,
+        };
+        
+        matches(&self.app, app) && matches(&self.window_class, window_class)
+    }
+}
+
+/// 捕获策略设置，对应 appsettings.json 的 Clipboard 部分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CapturePolicySettings {
+    /// 不保存这些程序复制的内容，来源程序包含该名称即匹配（不区分大小写）
+    pub excluded_applications: Vec<String>,
+    /// 按顺序匹配，使用第一个匹配的策略
+    pub app_policies: Vec<AppPolicy>,
+    /// 遵守密码管理器设置的隐藏提示
+    pub honor_concealed_hints: bool,
+}
+
+impl Default for CapturePolicySettings {
+    fn default() -> Self {
+        Self {
+            excluded_applications: vec![
+                "Password Manager".to_string(),
+                "Bitwarden".to_string(),
+                "KeePass".to_string(),
+            ],
+            app_policies: Vec::new(),
+            honor_concealed_hints: true,
+        }
+    }
+}
+
+impl CapturePolicySettings {
+    pub fn validate(&self) -> Result<(), String> {
+        for policy in &self.app_policies {
+            policy.validate()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 来源程序对应的处理方式，没有匹配的策略时返回 `None`
+    pub fn action_for(&self, app: Option<&str>, window_class: Option<&str>) -> Option<CaptureAction> {
+        if let Some(app) = app {
+            let app = app.to_lowercase();
+            let excluded = self.excluded_applications.iter()
+                .filter(|name| !name.trim().is_empty())
+                .any(|name| app.contains(&name.trim().to_lowercase()));
+            if excluded {
+                return Some(CaptureAction::Ignore);
+            }
+        }
+        
+        // 无效的策略不匹配任何来源
+        self.app_policies.iter()
+            .find(|policy| policy.validate().is_ok() && policy.matches(app, window_class))
+            .map(|policy| policy.action)
+    }
+}
+
+/// 是否为表示内容不应记录的提示格式
+pub fn is_hint_format(name: &str) -> bool {
+    CONCEALED_FORMATS.contains(&name) || name == CAN_INCLUDE_IN_HISTORY_FORMAT || name == KDE_PASSWORD_HINT_FORMAT
+}
+
+/// 是否保存该自定义格式，提示格式和 OLE 私有数据不保存
+pub fn is_capturable_format(name: &str) -> bool {
+    !is_hint_format(name) && !OLE_PRIVATE_FORMATS.contains(&name)
+}
+
+/// 剪贴板上是否有表示内容不应记录的提示，`read` 读取自定义格式的原始数据
+pub fn is_concealed(formats: &[ClipboardFormat], read: impl Fn(&ClipboardFormat) -> Option<Vec<u8>>) -> bool {
+    formats.iter().any(|format| {
+        let name = match format {
+            ClipboardFormat::Custom(name) => name.as_str(),
+            _ => return false,
+        };
+        
+        if CONCEALED_FORMATS.contains(&name) {
+            return true;
+        }
+        
+        match name {
+            CAN_INCLUDE_IN_HISTORY_FORMAT => read(format)
+                .is_some_and(|data| !data.is_empty() && data.iter().all(|byte| *byte == 0)),
+            KDE_PASSWORD_HINT_FORMAT => read(format)
+                .is_some_and(|data| String::from_utf8_lossy(&data).trim_end_matches('\0').trim() == "secret"),
+            _ => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn policy(app: Option<NamePattern>, window_class: Option<NamePattern>, action: CaptureAction) -> AppPolicy {
+        AppPolicy { app, window_class, action }
+    }
+    
+    fn custom(name: &str) -> ClipboardFormat {
+        ClipboardFormat::Custom(name.to_string())
+    }
+    
+    #[test]
+    fn name_patterns_ignore_case() {
+        assert!(NamePattern::Exact("KeePassXC".to_string()).matches("keepassxc"));
+        assert!(!NamePattern::Exact("KeePass".to_string()).matches("KeePassXC"));
+        
+        // 通配符需要匹配整个名称
+        let glob = NamePattern::Glob("*pass?".to_string());
+        assert!(glob.matches("KeePassX"));
+        assert!(glob.matches("KEEPASSX"));
+        assert!(!glob.matches("KeePass"));
+        assert!(!glob.matches("KeePassXC"));
+        assert!(!NamePattern::Glob("a.c".to_string()).matches("abc"));
+        
+        // 正则表达式匹配任意部分
+        let regex = NamePattern::Regex("^chrome_widget".to_string());
+        assert!(regex.matches("Chrome_WidgetWin_1"));
+        assert!(!regex.matches("MozillaWindowClass"));
+        assert!(!NamePattern::Regex("(".to_string()).matches("("));
+    }
+    
+    #[test]
+    fn policies_match_app_and_window_class() {
+        let both = policy(
+            Some(NamePattern::Exact("code".to_string())),
+            Some(NamePattern::Glob("Chrome_*".to_string())),
+            CaptureAction::TextOnly,
+        );
+        assert!(both.matches(Some("Code"), Some("Chrome_WidgetWin_1")));
+        assert!(!both.matches(Some("Code"), Some("ConsoleWindowClass")));
+        assert!(!both.matches(Some("Code"), None));
+        
+        let window_only = policy(None, Some(NamePattern::Regex("console".to_string())), CaptureAction::NoImages);
+        assert!(window_only.matches(None, Some("ConsoleWindowClass")));
+        assert!(!window_only.matches(Some("cmd"), None));
+    }
+    
+    #[test]
+    fn invalid_policies_are_rejected_and_never_match() {
+        assert!(policy(None, None, CaptureAction::Ignore).validate().is_err());
+        assert!(policy(Some(NamePattern::Exact("a".to_string())), None, CaptureAction::Expire(0)).validate().is_err());
+        assert!(policy(Some(NamePattern::Glob(" ".to_string())), None, CaptureAction::Ignore).validate().is_err());
+        
+        let invalid = policy(Some(NamePattern::Regex("[".to_string())), None, CaptureAction::Ignore);
+        assert!(invalid.validate().is_err());
+        let settings = CapturePolicySettings {
+            excluded_applications: Vec::new(),
+            app_policies: vec![invalid],
+            honor_concealed_hints: true,
+        };
+        assert!(settings.validate().is_err());
+        assert_eq!(settings.action_for(Some("["), None), None);
+    }
+    
+    #[test]
+    fn first_matching_policy_wins_after_exclusions() {
+        let settings = CapturePolicySettings {
+            app_policies: vec![
+                policy(Some(NamePattern::Glob("term*".to_string())), None, CaptureAction::Expire(60)),
+                policy(Some(NamePattern::Regex("term".to_string())), None, CaptureAction::NoImages),
+            ],
+            ..CapturePolicySettings::default()
+        };
+        
+        // 排除列表按名称包含匹配，优先于策略
+        assert_eq!(settings.action_for(Some("KeePassXC"), None), Some(CaptureAction::Ignore));
+        assert_eq!(settings.action_for(Some("Terminal"), None), Some(CaptureAction::Expire(60)));
+        assert_eq!(settings.action_for(Some("xterm"), None), Some(CaptureAction::NoImages));
+        assert_eq!(settings.action_for(Some("notepad"), None), None);
+        assert_eq!(settings.action_for(None, None), None);
+    }
+    
+    #[test]
+    fn actions_limit_the_captured_formats() {
+        let formats = [
+            ClipboardFormat::Text,
+            ClipboardFormat::Html,
+            ClipboardFormat::Image,
+            ClipboardFormat::Files,
+            custom("application/x-test"),
+        ];
+        let allowed = |action: CaptureAction| -> Vec<ClipboardFormat> {
+            formats.iter().filter(|format| action.allows(format)).cloned().collect()
+        };
+        
+        assert!(allowed(CaptureAction::Ignore).is_empty());
+        assert_eq!(allowed(CaptureAction::TextOnly), [ClipboardFormat::Text]);
+        assert!(!allowed(CaptureAction::NoImages).contains(&ClipboardFormat::Image));
+        assert_eq!(allowed(CaptureAction::NoImages).len(), 4);
+        assert_eq!(allowed(CaptureAction::Expire(60)).len(), 5);
+    }
+    
+    #[test]
+    fn concealed_hints_are_detected() {
+        let read_nothing = |_: &ClipboardFormat| None;
+        assert!(is_concealed(&[ClipboardFormat::Text, custom("org.nspasteboard.ConcealedType")], read_nothing));
+        assert!(is_concealed(&[custom("ExcludeClipboardContentFromMonitorProcessing")], read_nothing));
+        assert!(!is_concealed(&[ClipboardFormat::Text, custom("application/x-test")], read_nothing));
+        
+        // 需要读取值的提示
+        let history = [custom(CAN_INCLUDE_IN_HISTORY_FORMAT)];
+        assert!(is_concealed(&history, |_| Some(vec![0, 0, 0, 0])));
+        assert!(!is_concealed(&history, |_| Some(vec![1, 0, 0, 0])));
+        assert!(!is_concealed(&history, |_| None));
+        
+        let kde = [custom(KDE_PASSWORD_HINT_FORMAT)];
+        assert!(is_concealed(&kde, |_| Some(b"secret\0".to_vec())));
+        assert!(!is_concealed(&kde, |_| Some(b"public".to_vec())));
+    }
+    
+    #[test]
+    fn hint_and_ole_private_formats_are_not_captured() {
+        assert!(!is_capturable_format(CAN_INCLUDE_IN_HISTORY_FORMAT));
+        assert!(!is_capturable_format("Clipboard Viewer Ignore"));
+        assert!(!is_capturable_format("DataObject"));
+        assert!(!is_capturable_format("Ole Private Data"));
+        assert!(is_capturable_format("application/x-test"));
+    }
+}
@@ -0,0 +1,322 @@
+// 文本内容分类
+//
+// 捕获时识别文本的语义类型（链接、邮箱、颜色、JSON 等），写入元数据的 `kind`，
+// 源代码还会在 `language` 中记录猜测的语言。只根据文本本身判断，识别不出时
+// 不写入任何元数据。
+
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{ClipboardContent, ClipboardItem};
+
+/// 元数据中记录类型的键
+pub const KIND_KEY: &str = "kind";
+/// 元数据中记录代码语言的键
+pub const LANGUAGE_KEY: &str = "language";
+
+/// 超过该长度的文本只按代码判断，不再解析 JSON 等结构
+const MAX_STRUCTURED_LEN: usize = 256 * 1024;
+
+lazy_static! {
+    static ref URL: Regex = Regex::new(r"(?i)^(?:(?:https?|ftp)://|www\.)[^\s/$.?#][^\s]*$").unwrap();
+    static ref EMAIL: Regex = Regex::new(r"(?i)^(?:mailto:)?[a-z0-9._%+\-]+@[a-z0-9.\-]+\.[a-z]{2,}$").unwrap();
+    static ref PHONE: Regex = Regex::new(r"^\+?[0-9][0-9 \-().]{5,22}[0-9]$").unwrap();
+    static ref HEX_COLOR: Regex = Regex::new(r"^#(?:[0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8})$").unwrap();
+    static ref FUNC_COLOR: Regex = Regex::new(
+        r"(?i)^(?:rgba?|hsla?)\(\s*[0-9.]+(?:deg|%)?\s*[, ]\s*[0-9.]+%?\s*[, ]\s*[0-9.]+%?\s*(?:[,/]\s*[0-9.]+%?\s*)?\)$"
+    ).unwrap();
+    static ref UUID: Regex = Regex::new(
+        r"^\{?[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\}?$"
+    ).unwrap();
+    static ref WINDOWS_PATH: Regex = Regex::new(r#"^(?:[A-Za-z]:[\\/]|\\\\[^\\/\s]+[\\/])[^<>"|?*\r\n]*$"#).unwrap();
+    static ref UNIX_PATH: Regex = Regex::new(r"^(?:~|\.{1,2})?/[^\s/][^\r\n\x00]*$").unwrap();
+    static ref YAML_LINE: Regex = Regex::new(r#"^\s*(?:-\s+)?(?:[\w.\-]+|"[^"]*"|'[^']*')\s*:(?:\s|$)|^\s*-\s+\S"#).unwrap();
+    static ref XML_TAG: Regex = Regex::new(r"^<(?:\?xml|!DOCTYPE|[A-Za-z_][\w.\-:]*)[\s>/]").unwrap();
+}
+
+/// 识别出的文本类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContentKind {
+    Url,
+    Email,
+    Phone,
+    Color,
+    Json,
+    Xml,
+    Yaml,
+    FilePath,
+    Uuid,
+    Code,
+}
+
+impl ContentKind {
+    /// 写入元数据的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentKind::Url => "url",
+            ContentKind::Email => "email",
+            ContentKind::Phone => "phone",
+            ContentKind::Color => "color",
+            ContentKind::Json => "json",
+            ContentKind::Xml => "xml",
+            ContentKind::Yaml => "yaml",
+            ContentKind::FilePath => "path",
+            ContentKind::Uuid => "uuid",
+            ContentKind::Code => "code",
+        }
+    }
+    
+    /// 按名称解析，不区分大小写，也接受常见的别名
+    pub fn parse(name: &str) -> Option<Self> {
+        let kind = match name.to_lowercase().as_str() {
+            "url" | "link" => ContentKind::Url,
+            "email" | "mail" => ContentKind::Email,
+            "phone" | "tel" => ContentKind::Phone,
+            "color" | "colour" => ContentKind::Color,
+            "json" => ContentKind::Json,
+            "xml" => ContentKind::Xml,
+            "yaml" | "yml" => ContentKind::Yaml,
+            "path" | "filepath" => ContentKind::FilePath,
+            "uuid" | "guid" => ContentKind::Uuid,
+            "code" => ContentKind::Code,
+            _ => return None,
+        };
+        
+        Some(kind)
+    }
+}
+
+impl fmt::Display for ContentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 分类结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Classification {
+    pub kind: ContentKind,
+    /// 源代码猜测的语言
+    pub language: Option<&'static str>,
+}
+
+/// 识别文本的类型
+pub fn classify(text: &str) -> Option<Classification> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    
+    let kind = |kind| Some(Classification { kind, language: None });
+    
+    if !text.contains('\n') {
+        if UUID.is_match(text) {
+            return kind(ContentKind::Uuid);
+        }
+        if URL.is_match(text) {
+            return kind(ContentKind::Url);
+        }
+        if EMAIL.is_match(text) {
+            return kind(ContentKind::Email);
+        }
+        if HEX_COLOR.is_match(text) || FUNC_COLOR.is_match(text) {
+            return kind(ContentKind::Color);
+        }
+        if is_phone(text) {
+            return kind(ContentKind::Phone);
+        }
+        if WINDOWS_PATH.is_match(text) || UNIX_PATH.is_match(text) {
+            return kind(ContentKind::FilePath);
+        }
+    }
+    
+    if text.len() <= MAX_STRUCTURED_LEN {
+        if (text.starts_with('{') || text.starts_with('['))
+            && serde_json::from_str::<serde_json::Value>(text).is_ok()
+        {
+            return kind(ContentKind::Json);
+        }
+        if is_xml(text) {
+            return kind(ContentKind::Xml);
+        }
+        if is_yaml(text) {
+            return kind(ContentKind::Yaml);
+        }
+    }
+    
+    guess_language(text).map(|language| Classification {
+        kind: ContentKind::Code,
+        language: Some(language),
+    })
+}
+
+/// 对条目的文本分类并写入元数据，返回识别出的类型
+///
+/// 只处理以纯文本为主表示的条目。
+pub fn classify_item(item: &mut ClipboardItem) -> Option<ContentKind> {
+    let text = match &item.content {
+        ClipboardContent::Text(text) => text,
+        _ => return None,
+    };
+    
+    let classification = classify(text)?;
+    item.metadata.insert(KIND_KEY.to_string(), classification.kind.as_str().to_string());
+    if let Some(language) = classification.language {
+        item.metadata.insert(LANGUAGE_KEY.to_string(), language.to_string());
+    }
+    
+    Some(classification.kind)
+}
+
+fn is_phone(text: &str) -> bool {
+    let digits = text.chars().filter(char::is_ascii_digit).count();
+    // 纯数字可能只是一个数值，需要带有分隔符或 `+`
+    let formatted = text.starts_with('+') || text.contains([' ', '-', '(', '.']);
+    PHONE.is_match(text) && (7..=15).contains(&digits) && formatted
+}
+
+fn is_xml(text: &str) -> bool {
+    XML_TAG.is_match(text)
+        && text.ends_with('>')
+        && (text.starts_with("<?xml") || text.contains("</") || text.contains("/>"))
+}
+
+fn is_yaml(text: &str) -> bool {
+    let lines: Vec<&str> = text.lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#') && line.trim() != "---")
+        .collect();
+    if lines.len() < 2 {
+        return false;
+    }
+    
+    // 每一行都是 `key: value`、列表项或缩进的续行
+    let matched = lines.iter()
+        .filter(|line| YAML_LINE.is_match(line) || line.starts_with([' ', '\t']))
+        .count();
+    let keys = lines.iter().filter(|line| YAML_LINE.is_match(line)).count();
+    
+    matched == lines.len() && keys * 2 >= lines.len() && !text.contains(';') && !text.contains('{')
+}
+
+// 每种语言的特征，命中越多得分越高
+const LANGUAGE_HINTS: &[(&str, &[&str])] = &[
+    ("rust", &["fn ", "let mut ", "impl ", "pub fn ", "::", "-> ", "use std", "&self", "match ", "#[derive"]),
+    ("python", &["def ", "import ", "self.", "elif ", "print(", "__init__", "None", "from ", "lambda "]),
+    ("javascript", &["function ", "const ", "=> ", "console.log", "let ", "require(", "===", "document.", "export "]),
+    ("typescript", &["interface ", ": string", ": number", "export ", "=> ", "const ", "implements ", "readonly "]),
+    ("java", &["public class ", "System.out", "private ", "import java", "public static void", "@Override", "new "]),
+    ("csharp", &["using System", "namespace ", "public class ", "Console.", "private ", "var ", "async Task", "{ get;"]),
+    ("cpp", &["#include", "std::", "int main", "nullptr", "template<", "cout <<", "->", "::"]),
+    ("c", &["#include", "int main", "printf(", "malloc(", "->", "#define ", "struct "]),
+    ("go", &["func ", "package ", ":= ", "fmt.", "import (", "go ", "chan ", "defer "]),
+    ("sql", &["SELECT ", "FROM ", "WHERE ", "INSERT INTO", "UPDATE ", "CREATE TABLE", "JOIN ", "GROUP BY"]),
+    ("shell", &["#!/bin/", "echo ", "fi\n", "then", "export ", "$(", "sudo ", "&& ", "| grep"]),
+    ("powershell", &["Get-", "Set-", "$_", "Write-Host", "-ErrorAction", "param(", "$env:"]),
+    ("css", &["{\n", "px;", "color:", "margin:", "padding:", "display:", "font-"]),
+];
+
+/// 猜测源代码的语言，不像代码时返回 `None`
+fn guess_language(text: &str) -> Option<&'static str> {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    if lines.is_empty() {
+        return None;
+    }
+    
+    // 代码的行尾通常是这些符号之一
+    let code_lines = lines.iter()
+        .filter(|line| line.trim_end().ends_with([';', '{', '}', ')', ':', ']', ',']))
+        .count();
+    
+    // 同分时取靠前的语言
+    let (language, score) = LANGUAGE_HINTS.iter()
+        .rev()
+        .map(|(language, hints)| (*language, hints.iter().filter(|hint| text.contains(*hint)).count()))
+        .max_by_key(|(_, score)| *score)?;
+    
+    // 至少命中两个特征，并且不是普通的散文
+    if score >= 2 && (code_lines * 3 >= lines.len() || score >= 4) {
+        Some(language)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn kind(text: &str) -> Option<ContentKind> {
+        classify(text).map(|classification| classification.kind)
+    }
+    
+    #[test]
+    fn single_line_values_are_recognized() {
+        assert_eq!(kind("https://example.com/a?b=c"), Some(ContentKind::Url));
+        assert_eq!(kind("www.example.com"), Some(ContentKind::Url));
+        assert_eq!(kind("mailto:someone@example.org"), Some(ContentKind::Email));
+        assert_eq!(kind("+1 (555) 123-4567"), Some(ContentKind::Phone));
+        assert_eq!(kind("#ff8800"), Some(ContentKind::Color));
+        assert_eq!(kind("#f80c"), Some(ContentKind::Color));
+        assert_eq!(kind("rgba(255, 136, 0, 0.5)"), Some(ContentKind::Color));
+        assert_eq!(kind("{6F9619FF-8B86-D011-B42D-00C04FC964FF}"), Some(ContentKind::Uuid));
+        assert_eq!(kind(r"C:\Users\me\notes.txt"), Some(ContentKind::FilePath));
+        assert_eq!(kind("~/projects/clipboard"), Some(ContentKind::FilePath));
+    }
+    
+    #[test]
+    fn plain_text_and_numbers_are_not_classified() {
+        assert_eq!(kind("hello world"), None);
+        assert_eq!(kind("   "), None);
+        // 没有分隔符的纯数字只是数值
+        assert_eq!(kind("1234567890"), None);
+        assert_eq!(kind("#ff888"), None);
+        assert_eq!(kind("see https://example.com"), None);
+        assert_eq!(kind("I need to fix this: the build is broken."), None);
+    }
+    
+    #[test]
+    fn structured_text_is_recognized() {
+        assert_eq!(kind(r#"{"name": "clipboard", "tags": [1, 2]}"#), Some(ContentKind::Json));
+        assert_eq!(kind("{not json"), None);
+        assert_eq!(kind("<?xml version=\"1.0\"?><root/>"), Some(ContentKind::Xml));
+        assert_eq!(kind("<item id=\"1\">text</item>"), Some(ContentKind::Xml));
+        assert_eq!(kind("name: clipboard\nversion: 1\nfeatures:\n  - search\n  - tags"), Some(ContentKind::Yaml));
+    }
+    
+    #[test]
+    fn code_gets_a_language() {
+        let rust = classify("pub fn main() {\n    let mut count = 0;\n    println!(\"{}\", count);\n}").unwrap();
+        assert_eq!(rust, Classification { kind: ContentKind::Code, language: Some("rust") });
+        
+        let python = classify("def greet(name):\n    print(name)\n    return None").unwrap();
+        assert_eq!(python.language, Some("python"));
+        
+        let sql = classify("SELECT id, name\nFROM users\nWHERE active = 1;").unwrap();
+        assert_eq!(sql.language, Some("sql"));
+    }
+    
+    #[test]
+    fn only_text_items_get_metadata() {
+        let mut item = ClipboardItem::test_text("#abc");
+        assert_eq!(classify_item(&mut item), Some(ContentKind::Color));
+        assert_eq!(item.metadata[KIND_KEY], "color");
+        assert!(!item.metadata.contains_key(LANGUAGE_KEY));
+        
+        item.metadata.clear();
+        item.content = ClipboardContent::Html("https://example.com".to_string());
+        assert_eq!(classify_item(&mut item), None);
+        assert!(item.metadata.is_empty());
+    }
+    
+    #[test]
+    fn kind_names_round_trip() {
+        for kind in [ContentKind::Url, ContentKind::FilePath, ContentKind::Code] {
+            assert_eq!(ContentKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(ContentKind::parse("GUID"), Some(ContentKind::Uuid));
+        assert_eq!(ContentKind::parse("video"), None);
+    }
+}
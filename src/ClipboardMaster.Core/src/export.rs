@@ -0,0 +1,348 @@
+// 导出与导入
+//
+// JSON 包含完整的条目和集合，可以重新导入；CSV、HTML 和 Markdown 只用于查看，
+// 每个条目附带所属集合的名称。
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{ClipboardItem, Collection, Database, DuplicatePolicy, ExportFormat, SaveResult, SearchQuery, SearchSettings};
+
+/// 导出文件的格式版本
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ExportFile {
+    version: u32,
+    exported_at: DateTime<Utc>,
+    items: Vec<ClipboardItem>,
+    #[serde(default)]
+    collections: Vec<ExportedCollection>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExportedCollection {
+    id: Uuid,
+    name: String,
+    created_at: DateTime<Utc>,
+    /// 按顺序排列的条目 id
+    items: Vec<Uuid>,
+}
+
+impl Database {
+    pub fn export_items(&self, path: &Path, format: ExportFormat) -> Result<(), Box<dyn std::error::Error>> {
+        let items = self.search_items(SearchQuery::default(), &SearchSettings::default())?;
+        let count = items.len();
+        
+        let output = match format {
+            ExportFormat::Json => {
+                let mut collections = Vec::new();
+                for collection in self.get_collections()? {
+                    collections.push(ExportedCollection {
+                        items: self.get_collection_items(collection.id)?
+                            .into_iter()
+                            .map(|item| item.id)
+                            .collect(),
+                        id: collection.id,
+                        name: collection.name,
+                        created_at: collection.created_at,
+                    });
+                }
+                
+                serde_json::to_string_pretty(&ExportFile {
+                    version: EXPORT_VERSION,
+                    exported_at: Utc::now(),
+                    items,
+                    collections,
+                })?
+            }
+            ExportFormat::Csv => export_csv(&items, &self.collection_names()?),
+            ExportFormat::Html => export_html(&items, &self.collection_names()?),
+            ExportFormat::Markdown => export_markdown(&items, &self.collection_names()?),
+        };
+        
+        std::fs::write(path, output)?;
+        info!("Exported {} items to {}", count, path.display());
+        Ok(())
+    }
+    
+    /// 导入 JSON 导出文件，内容已存在的条目不重复保存，返回新增的条目数
+    ///
+    /// 集合按 id 或名称合并到已有集合，条目追加在末尾。
+    pub fn import_items(&self, path: &Path) -> Result<u32, Box<dyn std::error::Error>> {
+        let json = std::fs::read_to_string(path)?;
+        
+        // 兼容只包含条目数组的旧文件
+        let file = match serde_json::from_str::<ExportFile>(&json) {
+            Ok(file) => file,
+            Err(_) => ExportFile {
+                version: EXPORT_VERSION,
+                exported_at: Utc::now(),
+                items: serde_json::from_str(&json)
+                    .map_err(|e| format!("无法解析导入文件（只支持 JSON 格式）: {}", e))?,
+                collections: Vec::new(),
+            },
+        };
+        
+        if file.version > EXPORT_VERSION {
+            return Err(format!("导入文件版本 {} 高于当前程序支持的版本 {}", file.version, EXPORT_VERSION).into());
+        }
+        
+        // 导入文件中的 id 到数据库中 id 的映射
+        let mut ids = HashMap::new();
+        let mut imported = 0;
+        for item in file.items {
+            let id = item.id;
+            if self.get_item(id)?.is_some() {
+                ids.insert(id, id);
+                continue;
+            }
+            
+            match self.save_item(item, DuplicatePolicy::Ignore, 0)? {
+                SaveResult::Inserted => {
+                    imported += 1;
+                    ids.insert(id, id);
+                }
+                SaveResult::Ignored(existing) | SaveResult::MovedToTop(existing) => {
+                    ids.insert(id, existing);
+                }
+            }
+        }
+        
+        let existing = self.get_collections()?;
+        for exported in file.collections {
+            let target = existing.iter()
+                .find(|collection| collection.id == exported.id || collection.name.eq_ignore_ascii_case(&exported.name))
+                .map(|collection| collection.id);
+            
+            let collection_id = match target {
+                Some(id) => id,
+                None => {
+                    self.insert_collection(&Collection {
+                        id: exported.id,
+                        name: exported.name,
+                        created_at: exported.created_at,
+                        item_count: 0,
+                    })?;
+                    exported.id
+                }
+            };
+            
+            for item_id in exported.items.iter().filter_map(|id| ids.get(id)) {
+                self.add_to_collection(collection_id, *item_id, None)?;
+            }
+        }
+        
+        info!("Imported {} items from {}", imported, path.display());
+        Ok(imported)
+    }
+    
+    // 每个条目所属集合的名称
+    fn collection_names(&self) -> Result<HashMap<Uuid, Vec<String>>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT collection_items.item_id, collections.name FROM collection_items
+            JOIN collections ON collections.id = collection_items.collection_id
+            ORDER BY collections.name
+            "#
+        )?;
+        let rows = stmt.query_map(params![], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        
+        let mut names: HashMap<Uuid, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (item_id, name) = row?;
+            names.entry(Uuid::parse_str(&item_id)?).or_default().push(name);
+        }
+        
+        Ok(names)
+    }
+}
+
+fn content_type(item: &ClipboardItem) -> &str {
+    Database::content_type_name(&item.content)
+}
+
+// 完整文本，图片等没有文本的条目使用预览
+fn text(item: &ClipboardItem) -> String {
+    let text = Database::search_text(&item.representations());
+    if text.is_empty() { item.preview_text.clone() } else { text }
+}
+
+fn export_csv(items: &[ClipboardItem], collections: &HashMap<Uuid, Vec<String>>) -> String {
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+    
+    let mut output = String::from("id,timestamp,type,preview,tags,favorite,pinned,source_app,collections\r\n");
+    for item in items {
+        let row = [
+            item.id.to_string(),
+            item.timestamp.to_rfc3339(),
+            content_type(item).to_string(),
+            text(item),
+            item.tags.join("; "),
+            item.favorite.to_string(),
+            item.pinned.to_string(),
+            item.source_app.clone().unwrap_or_default(),
+            collections.get(&item.id).map(|names| names.join("; ")).unwrap_or_default(),
+        ];
+        output.push_str(&row.iter().map(|value| field(value)).collect::<Vec<_>>().join(","));
+        output.push_str("\r\n");
+    }
+    
+    output
+}
+
+fn export_html(items: &[ClipboardItem], collections: &HashMap<Uuid, Vec<String>>) -> String {
+    fn escape(value: &str) -> String {
+        value.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+    
+    let mut output = String::from(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Clipboard Master</title></head>\n<body>\n<table>\n\
+         <tr><th>时间</th><th>类型</th><th>内容</th><th>标签</th><th>集合</th></tr>\n"
+    );
+    for item in items {
+        output.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td><pre>{}</pre></td><td>{}</td><td>{}</td></tr>\n",
+            item.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            content_type(item),
+            escape(&text(item)),
+            escape(&item.tags.join(", ")),
+            escape(&collections.get(&item.id).map(|names| names.join(", ")).unwrap_or_default()),
+        ));
+    }
+    output.push_str("</table>\n</body>\n</html>\n");
+    
+    output
+}
+
+fn export_markdown(items: &[ClipboardItem], collections: &HashMap<Uuid, Vec<String>>) -> String {
+    fn cell(value: &str) -> String {
+        value.replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>")
+    }
+    
+    let mut output = String::from("| 时间 | 类型 | 内容 | 标签 | 集合 |\n| --- | --- | --- | --- | --- |\n");
+    for item in items {
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            item.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            content_type(item),
+            cell(&text(item)),
+            cell(&item.tags.join(", ")),
+            cell(&collections.get(&item.id).map(|names| names.join(", ")).unwrap_or_default()),
+        ));
+    }
+    
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ClipboardContent;
+    
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("clipboard-master-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+    
+    fn save_text(database: &Database, text: &str) -> Uuid {
+        let item = ClipboardItem::test_text(text);
+        let id = item.id;
+        database.save_item(item, DuplicatePolicy::KeepBoth, 0).unwrap();
+        id
+    }
+    
+    fn texts(items: Vec<ClipboardItem>) -> Vec<String> {
+        items.into_iter()
+            .map(|item| match item.content {
+                ClipboardContent::Text(text) => text,
+                content => panic!("unexpected content: {:?}", content),
+            })
+            .collect()
+    }
+    
+    fn collection_texts(database: &Database, name: &str) -> Vec<String> {
+        let collection = database.get_collections().unwrap()
+            .into_iter()
+            .find(|collection| collection.name.eq_ignore_ascii_case(name))
+            .unwrap();
+        texts(database.get_collection_items(collection.id).unwrap())
+    }
+    
+    // 条目 x、y、z，集合 Snippets 为 [y, x]，Work 为 [x]
+    fn exported() -> std::path::PathBuf {
+        let database = Database::new(":memory:").unwrap();
+        let x = save_text(&database, "x");
+        let y = save_text(&database, "y");
+        save_text(&database, "z");
+        
+        let snippets = Collection::new("Snippets");
+        let work = Collection::new("Work");
+        database.insert_collection(&snippets).unwrap();
+        database.insert_collection(&work).unwrap();
+        database.add_to_collection(snippets.id, y, None).unwrap();
+        database.add_to_collection(snippets.id, x, None).unwrap();
+        database.add_to_collection(work.id, x, None).unwrap();
+        
+        let path = temp_path("export.json");
+        database.export_items(&path, ExportFormat::Json).unwrap();
+        path
+    }
+    
+    #[test]
+    fn json_round_trip_keeps_collections() {
+        let path = exported();
+        
+        let database = Database::new(":memory:").unwrap();
+        assert_eq!(database.import_items(&path).unwrap(), 3);
+        
+        // 属于两个集合的条目只导入一次
+        let mut items = texts(database.search_items(SearchQuery::default(), &SearchSettings::default()).unwrap());
+        items.sort();
+        assert_eq!(items, ["x", "y", "z"]);
+        assert_eq!(collection_texts(&database, "Snippets"), ["y", "x"]);
+        assert_eq!(collection_texts(&database, "Work"), ["x"]);
+        
+        // 再次导入不产生重复
+        assert_eq!(database.import_items(&path).unwrap(), 0);
+        assert_eq!(database.get_collections().unwrap().len(), 2);
+        assert_eq!(collection_texts(&database, "Snippets"), ["y", "x"]);
+    }
+    
+    #[test]
+    fn import_merges_into_collections_with_the_same_name() {
+        let path = exported();
+        
+        let database = Database::new(":memory:").unwrap();
+        let existing = Collection::new("snippets");
+        database.insert_collection(&existing).unwrap();
+        let w = save_text(&database, "w");
+        // 内容相同的条目使用已有条目
+        let y = save_text(&database, "y");
+        database.add_to_collection(existing.id, w, None).unwrap();
+        database.add_to_collection(existing.id, y, None).unwrap();
+        
+        assert_eq!(database.import_items(&path).unwrap(), 2);
+        
+        let names: Vec<String> = database.get_collections().unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, ["snippets", "Work"]);
+        assert_eq!(collection_texts(&database, "snippets"), ["w", "y", "x"]);
+    }
+}
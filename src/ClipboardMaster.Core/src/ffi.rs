@@ -1,8 +1,10 @@
-use std::ffi::{c_void, CStr, CString};
+use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 use std::sync::Arc;
+use std::time::Duration;
 use parking_lot::RwLock;
-use crate::{ClipboardCore, ClipboardItem, SearchQuery, AppSettings};
+use uuid::Uuid;
+use crate::{ClipboardCore, CopyOptions, SearchQuery, AppSettings};
 
 static mut CORE: Option<Arc<RwLock<Option<ClipboardCore>>>> = None;
 
@@ -165,6 +167,350 @@ pub extern "C" fn clipboard_core_get_recent_items(limit: u32) -> *mut c_char {
     }
 }
 
+#[no_mangle]
+pub extern "C" fn clipboard_core_get_recent_searches(limit: u32) -> *mut c_char {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match core.get_recent_searches(limit) {
+                    Ok(entries) => {
+                        match serde_json::to_string(&entries) {
+                            Ok(json) => {
+                                let c_string = CString::new(json).unwrap();
+                                c_string.into_raw()
+                            }
+                            Err(e) => {
+                                log::error!("序列化搜索历史失败: {}", e);
+                                std::ptr::null_mut()
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("获取搜索历史失败: {}", e);
+                        std::ptr::null_mut()
+                    }
+                }
+            } else {
+                std::ptr::null_mut()
+            }
+        } else {
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn clipboard_core_get_frequent_searches(limit: u32) -> *mut c_char {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match core.get_frequent_searches(limit) {
+                    Ok(entries) => {
+                        match serde_json::to_string(&entries) {
+                            Ok(json) => {
+                                let c_string = CString::new(json).unwrap();
+                                c_string.into_raw()
+                            }
+                            Err(e) => {
+                                log::error!("序列化搜索历史失败: {}", e);
+                                std::ptr::null_mut()
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("获取搜索历史失败: {}", e);
+                        std::ptr::null_mut()
+                    }
+                }
+            } else {
+                std::ptr::null_mut()
+            }
+        } else {
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// 搜索框自动完成，返回以 `prefix` 开头的历史查询
+///
+/// # Safety
+///
+/// `prefix` 必须为空指针或指向以 NUL 结尾的有效字符串，调用期间不能被释放或修改。
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_core_complete_search(prefix: *const c_char, limit: u32) -> *mut c_char {
+    unsafe {
+        if prefix.is_null() {
+            return std::ptr::null_mut();
+        }
+        
+        if let Some(core_ref) = &CORE {
+            let c_str = CStr::from_ptr(prefix);
+            let prefix_str = match c_str.to_str() {
+                Ok(s) => s,
+                Err(_) => return std::ptr::null_mut(),
+            };
+            
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match core.complete_search(prefix_str, limit) {
+                    Ok(entries) => {
+                        match serde_json::to_string(&entries) {
+                            Ok(json) => {
+                                let c_string = CString::new(json).unwrap();
+                                c_string.into_raw()
+                            }
+                            Err(e) => {
+                                log::error!("序列化搜索历史失败: {}", e);
+                                std::ptr::null_mut()
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("获取搜索历史失败: {}", e);
+                        std::ptr::null_mut()
+                    }
+                }
+            } else {
+                std::ptr::null_mut()
+            }
+        } else {
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn clipboard_core_clear_search_history() -> bool {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match core.clear_search_history() {
+                    Ok(_) => true,
+                    Err(e) => {
+                        log::error!("清除搜索历史失败: {}", e);
+                        false
+                    }
+                }
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    }
+}
+
+/// 全部标签及使用数量
+#[no_mangle]
+pub extern "C" fn clipboard_core_get_tags() -> *mut c_char {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match core.get_tags() {
+                    Ok(tags) => {
+                        match serde_json::to_string(&tags) {
+                            Ok(json) => {
+                                let c_string = CString::new(json).unwrap();
+                                c_string.into_raw()
+                            }
+                            Err(e) => {
+                                log::error!("序列化标签失败: {}", e);
+                                std::ptr::null_mut()
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("获取标签失败: {}", e);
+                        std::ptr::null_mut()
+                    }
+                }
+            } else {
+                std::ptr::null_mut()
+            }
+        } else {
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// 对已有条目重新执行自动标签规则，返回修改的条目数，失败时返回 -1
+#[no_mangle]
+pub extern "C" fn clipboard_core_apply_tag_rules() -> i32 {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match core.apply_tag_rules() {
+                    Ok(count) => count as i32,
+                    Err(e) => {
+                        log::error!("执行自动标签规则失败: {}", e);
+                        -1
+                    }
+                }
+            } else {
+                -1
+            }
+        } else {
+            -1
+        }
+    }
+}
+
+/// 暂停捕获，`duration_secs` 为 0 时一直暂停到调用 `clipboard_core_resume_capture`
+#[no_mangle]
+pub extern "C" fn clipboard_core_pause_capture(duration_secs: u32) -> bool {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                let duration = (duration_secs > 0).then(|| Duration::from_secs(u64::from(duration_secs)));
+                match core.pause_capture(duration) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        log::error!("暂停捕获失败: {}", e);
+                        false
+                    }
+                }
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn clipboard_core_resume_capture() -> bool {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                core.resume_capture();
+                true
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    }
+}
+
+/// 当前的暂停状态，返回 `{"paused":true,"resume_at":"..."}`
+#[no_mangle]
+pub extern "C" fn clipboard_core_get_capture_state() -> *mut c_char {
+    unsafe {
+        if let Some(core_ref) = &CORE {
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match serde_json::to_string(&core.capture_state()) {
+                    Ok(json) => {
+                        let c_string = CString::new(json).unwrap();
+                        c_string.into_raw()
+                    }
+                    Err(e) => {
+                        log::error!("序列化暂停状态失败: {}", e);
+                        std::ptr::null_mut()
+                    }
+                }
+            } else {
+                std::ptr::null_mut()
+            }
+        } else {
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// 解析查询语言，返回 `{"ok":true,"query":{...}}` 或
+/// `{"ok":false,"error":{"message":"...","position":N}}`
+///
+/// # Safety
+///
+/// `input` 必须为空指针或指向以 NUL 结尾的有效字符串，调用期间不能被释放或修改。
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_core_parse_query(input: *const c_char) -> *mut c_char {
+    unsafe {
+        if input.is_null() {
+            return std::ptr::null_mut();
+        }
+        
+        let c_str = CStr::from_ptr(input);
+        let input_str = match c_str.to_str() {
+            Ok(s) => s,
+            Err(_) => return std::ptr::null_mut(),
+        };
+        
+        let result = match SearchQuery::parse(input_str) {
+            Ok(query) => serde_json::json!({ "ok": true, "query": query }),
+            Err(e) => serde_json::json!({ "ok": false, "error": e }),
+        };
+        
+        match serde_json::to_string(&result) {
+            Ok(json) => {
+                let c_string = CString::new(json).unwrap();
+                c_string.into_raw()
+            }
+            Err(e) => {
+                log::error!("序列化查询失败: {}", e);
+                std::ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// 将历史条目写回系统剪贴板，`options_json` 为 `CopyOptions`，空指针时使用默认选项
+///
+/// # Safety
+///
+/// `id` 必须指向以 NUL 结尾的有效字符串，`options_json` 必须为空指针或指向以 NUL 结尾的有效字符串，
+/// 调用期间不能被释放或修改。
+#[no_mangle]
+pub unsafe extern "C" fn clipboard_core_copy_to_clipboard(id: *const c_char, options_json: *const c_char) -> bool {
+    unsafe {
+        if id.is_null() {
+            return false;
+        }
+        
+        if let Some(core_ref) = &CORE {
+            let id = match CStr::from_ptr(id).to_str().ok().and_then(|s| Uuid::parse_str(s).ok()) {
+                Some(id) => id,
+                None => return false,
+            };
+            
+            let options = if options_json.is_null() {
+                CopyOptions::default()
+            } else {
+                let c_str = CStr::from_ptr(options_json);
+                match c_str.to_str().ok().and_then(|s| serde_json::from_str(s).ok()) {
+                    Some(options) => options,
+                    None => return false,
+                }
+            };
+            
+            let core_guard = core_ref.read();
+            if let Some(core) = core_guard.as_ref() {
+                match core.copy_to_clipboard(id, options) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        log::error!("写回剪贴板失败: {}", e);
+                        false
+                    }
+                }
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn clipboard_core_free_string(ptr: *mut c_char) {
     unsafe {
@@ -0,0 +1,64 @@
+// 使用频率与新近程度的综合得分
+//
+// 得分 = (1 + 使用次数) × 0.5 ^ (距最后使用的时间 / 半衰期)
+//
+// 内置的 SQLite 没有数学函数，因此注册 `frecency(access_count, last_used, now, half_life_hours)`
+// 供排序使用，模糊搜索在内存中排序时直接调用 `score`。
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+/// 计算得分，`age_secs` 为距最后使用的秒数
+pub fn score(access_count: i64, age_secs: i64, half_life_hours: f64) -> f64 {
+    let count = (1 + access_count.max(0)) as f64;
+    if half_life_hours <= 0.0 {
+        return count;
+    }
+    
+    let age_hours = age_secs.max(0) as f64 / 3600.0;
+    count * 0.5f64.powf(age_hours / half_life_hours)
+}
+
+/// 在连接上注册 `frecency` 函数
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "frecency",
+        4,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let access_count: i64 = ctx.get::<Option<i64>>(0)?.unwrap_or(0);
+            let last_used: i64 = ctx.get(1)?;
+            let now: i64 = ctx.get(2)?;
+            let half_life_hours: f64 = ctx.get(3)?;
+            
+            Ok(score(access_count, now - last_used, half_life_hours))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn score_halves_every_half_life() {
+        assert_eq!(score(0, 0, 72.0), 1.0);
+        assert_eq!(score(3, 72 * 3600, 72.0), 2.0);
+        assert_eq!(score(3, 144 * 3600, 72.0), 1.0);
+        
+        // 半衰期为 0 时只看次数，负数视为 0
+        assert_eq!(score(4, 1_000_000, 0.0), 5.0);
+        assert_eq!(score(-1, -10, 72.0), 1.0);
+    }
+    
+    #[test]
+    fn sql_function_matches_score() {
+        let conn = Connection::open_in_memory().unwrap();
+        register(&conn).unwrap();
+        
+        let value: f64 = conn
+            .query_row("SELECT frecency(NULL, 0, 72 * 3600, 72.0)", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(value, score(0, 72 * 3600, 72.0));
+    }
+}
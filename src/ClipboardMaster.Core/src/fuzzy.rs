@@ -0,0 +1,219 @@
+// 模糊匹配
+//
+// 类似 Smith-Waterman 的局部对齐：模式串必须全部参与对齐，文本首尾不计分。
+// 允许跳过文本字符（间隔）、跳过模式字符、错配或相邻字符互换（容忍拼写错误），
+// 连续命中和单词开头的命中有额外加分，与 fzf 的打分方式相近。
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_MISMATCH: i32 = -8;
+const PENALTY_SKIP_PATTERN: i32 = 10;
+const PENALTY_TRANSPOSE: i32 = 8;
+const PENALTY_GAP: i32 = 2;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CONSECUTIVE: i32 = 4;
+
+/// 匹配结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    /// 0 到 1 之间的得分，1 表示完整连续命中
+    pub score: f64,
+    /// 文本中命中字符的位置（按字符计）
+    pub positions: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    Start,
+    Diagonal,
+    Transpose,
+    SkipPattern,
+    SkipText,
+}
+
+/// 对文本进行模糊匹配，得分低于 `threshold` 时返回 `None`
+pub fn fuzzy_match(pattern: &str, text: &str, threshold: f64) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(lower)
+        .collect();
+    if pattern.is_empty() {
+        return None;
+    }
+    
+    let original: Vec<char> = text.chars().collect();
+    let text: Vec<char> = original.iter().copied().map(lower).collect();
+    
+    // 快速排除：文本中出现的模式字符太少时不可能达到阈值
+    let present = pattern.iter().filter(|c| text.contains(c)).count();
+    if (present as f64) < pattern.len() as f64 * threshold {
+        return None;
+    }
+    
+    let (m, n) = (pattern.len(), text.len());
+    let width = n + 1;
+    let mut score = vec![0i32; (m + 1) * width];
+    let mut run = vec![0u16; (m + 1) * width];
+    let mut step = vec![Step::Start; (m + 1) * width];
+    
+    for i in 1..=m {
+        score[i * width] = -PENALTY_SKIP_PATTERN * i as i32;
+        step[i * width] = Step::SkipPattern;
+        
+        for j in 1..=n {
+            let diag_idx = (i - 1) * width + j - 1;
+            let matched = pattern[i - 1] == text[j - 1];
+            
+            let diagonal = if matched {
+                let mut s = SCORE_MATCH;
+                if run[diag_idx] > 0 {
+                    s += BONUS_CONSECUTIVE;
+                }
+                if is_boundary(&original, j - 1) {
+                    s += BONUS_BOUNDARY;
+                }
+                score[diag_idx] + s
+            } else {
+                score[diag_idx] + SCORE_MISMATCH
+            };
+            let skip_pattern = score[(i - 1) * width + j] - PENALTY_SKIP_PATTERN;
+            let skip_text = score[i * width + j - 1] - PENALTY_GAP;
+            
+            // 相邻两个字符互换，如 "clipbaord"
+            let transpose = if i >= 2 && j >= 2
+                && pattern[i - 1] == text[j - 2]
+                && pattern[i - 2] == text[j - 1]
+                && pattern[i - 1] != pattern[i - 2]
+            {
+                Some(score[(i - 2) * width + j - 2] + 2 * SCORE_MATCH - PENALTY_TRANSPOSE)
+            } else {
+                None
+            };
+            
+            let idx = i * width + j;
+            if let Some(transpose) = transpose.filter(|&t| t > diagonal && t >= skip_pattern && t >= skip_text) {
+                score[idx] = transpose;
+                step[idx] = Step::Transpose;
+                run[idx] = run[(i - 2) * width + j - 2] + 2;
+            } else if diagonal >= skip_pattern && diagonal >= skip_text {
+                score[idx] = diagonal;
+                step[idx] = Step::Diagonal;
+                run[idx] = if matched { run[diag_idx] + 1 } else { 0 };
+            } else if skip_text >= skip_pattern {
+                score[idx] = skip_text;
+                step[idx] = Step::SkipText;
+            } else {
+                score[idx] = skip_pattern;
+                step[idx] = Step::SkipPattern;
+            }
+        }
+    }
+    
+    // 文本末尾不计分，取最后一行的最大值
+    let (end, best) = (0..=n)
+        .map(|j| (j, score[m * width + j]))
+        .max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))?;
+    
+    let perfect = SCORE_MATCH * m as i32 + BONUS_CONSECUTIVE * (m as i32 - 1) + BONUS_BOUNDARY;
+    let normalized = (best as f64 / perfect as f64).clamp(0.0, 1.0);
+    if normalized < threshold {
+        return None;
+    }
+    
+    // 回溯命中位置
+    let mut positions = Vec::new();
+    let (mut i, mut j) = (m, end);
+    while i > 0 && j > 0 {
+        match step[i * width + j] {
+            Step::Diagonal => {
+                if pattern[i - 1] == text[j - 1] {
+                    positions.push(j - 1);
+                }
+                i -= 1;
+                j -= 1;
+            }
+            Step::Transpose => {
+                positions.push(j - 1);
+                positions.push(j - 2);
+                i -= 2;
+                j -= 2;
+            }
+            Step::SkipPattern => i -= 1,
+            Step::SkipText => j -= 1,
+            Step::Start => break,
+        }
+    }
+    positions.reverse();
+    
+    Some(FuzzyMatch {
+        score: normalized,
+        positions,
+    })
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// 单词开头：文本开头、分隔符之后或小写到大写的转换处
+fn is_boundary(text: &[char], index: usize) -> bool {
+    if index == 0 {
+        return true;
+    }
+    
+    let (prev, current) = (text[index - 1], text[index]);
+    !prev.is_alphanumeric() || (prev.is_lowercase() && current.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn exact_word_match_scores_one() {
+        let result = fuzzy_match("clipboard", "Clipboard", 0.5).unwrap();
+        assert_eq!(result.score, 1.0);
+        assert_eq!(result.positions, (0..9).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    fn typos_still_match_with_a_lower_score() {
+        let exact = fuzzy_match("clipboard", "copy to clipboard", 0.0).unwrap();
+        
+        // 相邻字符互换
+        let transposed = fuzzy_match("clipbaord", "copy to clipboard", 0.5).unwrap();
+        assert!(transposed.score < exact.score);
+        assert_eq!(transposed.positions, exact.positions);
+        
+        // 漏掉一个字符
+        let missing = fuzzy_match("clipbard", "copy to clipboard", 0.5).unwrap();
+        assert!(missing.score < exact.score);
+        assert_eq!(missing.positions, [8, 9, 10, 11, 12, 14, 15, 16]);
+    }
+    
+    #[test]
+    fn scores_below_the_threshold_are_rejected() {
+        let score = fuzzy_match("clpbrd", "clipboard", 0.0).unwrap().score;
+        assert!(score > 0.0 && score < 1.0);
+        assert!(fuzzy_match("clpbrd", "clipboard", score).is_some());
+        assert!(fuzzy_match("clpbrd", "clipboard", score + 0.01).is_none());
+        
+        assert!(fuzzy_match("xyz", "clipboard", 0.1).is_none());
+        assert!(fuzzy_match("  ", "clipboard", 0.0).is_none());
+    }
+    
+    #[test]
+    fn positions_are_character_indices() {
+        // 多字节字符按字符计位置，界面据此高亮
+        let result = fuzzy_match("剪贴板", "复制到剪贴板", 0.5).unwrap();
+        assert_eq!(result.positions, [3, 4, 5]);
+        
+        let result = fuzzy_match("café", "über café", 0.5).unwrap();
+        assert_eq!(result.positions, [5, 6, 7, 8]);
+        let highlighted: String = "über café".chars()
+            .enumerate()
+            .filter(|(i, _)| result.positions.contains(i))
+            .map(|(_, c)| c)
+            .collect();
+        assert_eq!(highlighted, "café");
+    }
+}
@@ -0,0 +1,298 @@
+// HTML 剪贴板内容处理
+//
+// Windows 的 "HTML Format"（CF_HTML）在 HTML 前附带一段描述头：
+//
+//     Version:0.9
+//     StartHTML:0000000105
+//     EndHTML:0000000199
+//     StartFragment:0000000141
+//     EndFragment:0000000163
+//     SourceURL:https://example.com/
+//
+// 偏移量均为相对整个负载开头的 UTF-8 字节偏移。这里负责解析与生成该格式，
+// 并从 HTML 生成纯文本预览。
+
+/// 解析后的 CF_HTML 负载
+#[derive(Debug, Clone, PartialEq)]
+pub struct CfHtml {
+    pub version: String,
+    pub source_url: Option<String>,
+    /// StartHTML 到 EndHTML 之间的完整文档
+    pub html: String,
+    /// StartFragment 到 EndFragment 之间的选中内容
+    pub fragment: String,
+}
+
+/// 判断内容是否带有 CF_HTML 描述头
+pub fn is_cf_html(payload: &str) -> bool {
+    payload.starts_with("Version:")
+}
+
+/// 解析 CF_HTML 负载，不带描述头时返回 `None`
+///
+/// 偏移量缺失或越界时（部分程序写入 -1），退回到查找
+/// `<!--StartFragment-->` / `<!--EndFragment-->` 注释。
+pub fn parse_cf_html(payload: &str) -> Option<CfHtml> {
+    if !is_cf_html(payload) {
+        return None;
+    }
+    
+    let mut version = String::new();
+    let mut source_url = None;
+    let mut offsets: [Option<usize>; 4] = [None; 4];
+    
+    for line in payload.lines() {
+        // 描述头在第一个标签之前结束
+        if line.starts_with('<') {
+            break;
+        }
+        
+        let (key, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let value = value.trim();
+        
+        match key {
+            "Version" => version = value.to_string(),
+            "SourceURL" if !value.is_empty() => source_url = Some(value.to_string()),
+            "StartHTML" => offsets[0] = value.parse().ok(),
+            "EndHTML" => offsets[1] = value.parse().ok(),
+            "StartFragment" => offsets[2] = value.parse().ok(),
+            "EndFragment" => offsets[3] = value.parse().ok(),
+            _ => {}
+        }
+    }
+    
+    let slice = |start: Option<usize>, end: Option<usize>| -> Option<&str> {
+        let (start, end) = (start?, end?);
+        if start <= end { payload.get(start..end) } else { None }
+    };
+    
+    let html = slice(offsets[0], offsets[1])
+        .or_else(|| payload.find('<').map(|start| &payload[start..]))
+        .unwrap_or("")
+        .to_string();
+    
+    let fragment = slice(offsets[2], offsets[3])
+        .map(str::to_string)
+        .or_else(|| fragment_between_markers(payload))
+        .unwrap_or_else(|| html.clone());
+    
+    Some(CfHtml {
+        version,
+        source_url,
+        html,
+        fragment,
+    })
+}
+
+fn fragment_between_markers(payload: &str) -> Option<String> {
+    const START: &str = "<!--StartFragment-->";
+    const END: &str = "<!--EndFragment-->";
+    
+    let start = payload.find(START)? + START.len();
+    let end = start + payload[start..].find(END)?;
+    Some(payload[start..end].to_string())
+}
+
+/// 将 HTML 片段包装为 CF_HTML 负载
+pub fn build_cf_html(fragment: &str, source_url: Option<&str>) -> String {
+    const PREFIX: &str = "<html><body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body></html>";
+    
+    // 偏移量固定为 10 位数字，描述头长度因此与具体数值无关
+    let source_line = source_url
+        .map(|url| format!("SourceURL:{}\r\n", url))
+        .unwrap_or_default();
+    let header_len = format!(
+        "Version:0.9\r\nStartHTML:{0:010}\r\nEndHTML:{0:010}\r\nStartFragment:{0:010}\r\nEndFragment:{0:010}\r\n{1}",
+        0, source_line
+    ).len();
+    
+    let start_html = header_len;
+    let start_fragment = start_html + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+    
+    format!(
+        "Version:0.9\r\nStartHTML:{:010}\r\nEndHTML:{:010}\r\nStartFragment:{:010}\r\nEndFragment:{:010}\r\n{}{}{}{}",
+        start_html, end_html, start_fragment, end_fragment, source_line, PREFIX, fragment, SUFFIX
+    )
+}
+
+/// 从 HTML 提取纯文本：去掉标签、脚本和样式，解码实体，块级元素换行
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    
+    while let Some(pos) = rest.find(['<', '&']) {
+        text.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        
+        if rest.starts_with('&') {
+            let (decoded, consumed) = decode_entity(rest);
+            text.push_str(&decoded);
+            rest = &rest[consumed..];
+            continue;
+        }
+        
+        // 注释
+        if rest.starts_with("<!--") {
+            rest = match rest.find("-->") {
+                Some(end) => &rest[end + 3..],
+                None => "",
+            };
+            continue;
+        }
+        
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => {
+                // 不完整的标签按文本处理
+                text.push_str(rest);
+                rest = "";
+                break;
+            }
+        };
+        
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        
+        let name: String = tag.trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        
+        match name.as_str() {
+            // 跳过脚本和样式的内容
+            "script" | "style" | "head" | "title" if !tag.starts_with('/') => {
+                let close = format!("</{}", name);
+                rest = match find_ignore_case(rest, &close) {
+                    Some(pos) => match rest[pos..].find('>') {
+                        Some(end) => &rest[pos + end + 1..],
+                        None => "",
+                    },
+                    None => "",
+                };
+            }
+            "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+            | "ul" | "ol" | "table" | "blockquote" | "pre" | "hr" if !text.ends_with('\n') => {
+                text.push('\n');
+            }
+            "td" | "th" if tag.starts_with('/') => text.push('\t'),
+            _ => {}
+        }
+    }
+    text.push_str(rest);
+    
+    collapse_whitespace(&text)
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.to_ascii_lowercase().find(&needle.to_ascii_lowercase())
+}
+
+/// 解码 `&...;` 实体，返回解码结果及消耗的字节数
+fn decode_entity(input: &str) -> (String, usize) {
+    let end = match input[1..].find(|c: char| c == ';' || c == '&' || c == '<' || c.is_whitespace()) {
+        Some(pos) if input[1 + pos..].starts_with(';') && pos <= 10 => pos + 1,
+        _ => return ("&".to_string(), 1),
+    };
+    
+    let entity = &input[1..end];
+    let decoded = match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ if entity.starts_with("#x") || entity.starts_with("#X") => {
+            u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32)
+        }
+        _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+        _ => None,
+    };
+    
+    match decoded {
+        Some(c) => (c.to_string(), end + 1),
+        None => ("&".to_string(), 1),
+    }
+}
+
+/// 行内连续空白合并为一个空格，去掉空行和首尾空白
+fn collapse_whitespace(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn build_then_parse_round_trips() {
+        let payload = build_cf_html("<b>你好</b> world", None);
+        let parsed = parse_cf_html(&payload).unwrap();
+        
+        assert_eq!(parsed.version, "0.9");
+        assert_eq!(parsed.source_url, None);
+        assert_eq!(parsed.fragment, "<b>你好</b> world");
+        assert!(parsed.html.starts_with("<html>"));
+        assert!(parsed.html.ends_with("</html>"));
+    }
+    
+    #[test]
+    fn build_then_parse_keeps_source_url() {
+        let payload = build_cf_html("<i>quote</i>", Some("https://example.com/page?a=1"));
+        let parsed = parse_cf_html(&payload).unwrap();
+        
+        assert_eq!(parsed.source_url.as_deref(), Some("https://example.com/page?a=1"));
+        assert_eq!(parsed.fragment, "<i>quote</i>");
+    }
+    
+    #[test]
+    fn invalid_offsets_fall_back_to_markers() {
+        let body = "<html><body><!--StartFragment--><p>fragment</p><!--EndFragment--></body></html>";
+        
+        let payload = format!(
+            "Version:0.9\r\nStartHTML:-1\r\nEndHTML:-1\r\nStartFragment:-1\r\nEndFragment:-1\r\n{}",
+            body
+        );
+        let parsed = parse_cf_html(&payload).unwrap();
+        assert_eq!(parsed.fragment, "<p>fragment</p>");
+        assert_eq!(parsed.html, body);
+        
+        let payload = format!(
+            "Version:0.9\r\nStartHTML:0000000090\r\nEndHTML:0000099999\r\nStartFragment:0000099000\r\nEndFragment:0000099999\r\n{}",
+            body
+        );
+        let parsed = parse_cf_html(&payload).unwrap();
+        assert_eq!(parsed.fragment, "<p>fragment</p>");
+    }
+    
+    #[test]
+    fn non_cf_html_is_not_parsed() {
+        assert!(parse_cf_html("<b>plain html</b>").is_none());
+    }
+    
+    #[test]
+    fn html_to_text_decodes_entities() {
+        assert_eq!(html_to_text("a &amp; b &lt;c&gt; &quot;d&quot; &#65;&#x42; &nbsp;e"), "a & b <c> \"d\" AB e");
+        // 无法识别的实体保持原样
+        assert_eq!(html_to_text("AT&T &unknown; x"), "AT&T &unknown; x");
+    }
+    
+    #[test]
+    fn html_to_text_strips_script_and_style() {
+        let html = "<html><head><style>p { color: red; }</style></head><body>\
+                    <p>first</p><script type=\"text/javascript\">alert('x')</script>\
+                    <div>second<br>third</div><!-- comment --></body></html>";
+        assert_eq!(html_to_text(html), "first\nsecond\nthird");
+    }
+}
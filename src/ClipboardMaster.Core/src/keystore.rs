@@ -0,0 +1,37 @@
+// 系统凭据存储
+//
+// 敏感信息的加密密钥不写入 config.json，而是保存在系统的凭据存储中：Windows 使用
+// 凭据管理器（由 DPAPI 按用户加密），Linux 使用 Secret Service（GNOME Keyring、KWallet 等）。
+
+use keyring::Entry;
+
+const SERVICE: &str = "ClipboardMaster";
+const ACCOUNT: &str = "sensitive-encryption-key";
+
+fn entry() -> Result<Entry, String> {
+    Entry::new(SERVICE, ACCOUNT).map_err(|e| format!("无法访问系统凭据存储: {}", e))
+}
+
+/// 读取加密密钥，没有保存过时返回 `None`
+pub fn load_encryption_key() -> Result<Option<String>, String> {
+    match entry()?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("无法读取加密密钥: {}", e)),
+    }
+}
+
+/// 保存加密密钥，为空时删除已保存的密钥
+pub fn store_encryption_key(key: &str) -> Result<(), String> {
+    let entry = entry()?;
+    let result = if key.is_empty() {
+        match entry.delete_password() {
+            Err(keyring::Error::NoEntry) => Ok(()),
+            result => result,
+        }
+    } else {
+        entry.set_password(key)
+    };
+    
+    result.map_err(|e| format!("无法保存加密密钥: {}", e))
+}
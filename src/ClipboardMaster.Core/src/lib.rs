@@ -3,14 +3,39 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime};
-use parking_lot::RwLock;
+use std::time::{Duration, Instant};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use bytes::Bytes;
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
 use log::{info, warn, error};
+use rusqlite::{params, OptionalExtension};
+
+mod appsettings;
+pub mod backend;
+pub mod capture_policy;
+pub mod classify;
+pub mod fuzzy;
+mod export;
+pub mod ffi;
+mod frecency;
+pub mod html;
+mod keystore;
+mod migrations;
+pub mod query;
+mod regexp;
+pub mod rules;
+pub mod sensitive;
+pub mod tags;
+
+pub use backend::{ClipboardBackend, ClipboardFormat, InMemoryBackend, SELF_WRITE_FORMAT};
+pub use capture_policy::{AppPolicy, CaptureAction, CapturePolicySettings, NamePattern};
+pub use classify::ContentKind;
+pub use query::{QueryExpr, QueryParseError};
+pub use rules::{RuleActions, RuleConditions, TagRule};
+pub use sensitive::{SensitiveKind, SensitivePolicy, SensitiveSettings};
+pub use tags::TagInfo;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClipboardContent {
@@ -43,8 +68,127 @@ pub enum ImageFormat {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileItem {
     pub path: PathBuf,
+    /// 文件大小；目录为其下所有文件的总大小
     pub size: u64,
     pub modified: DateTime<Utc>,
+    #[serde(default)]
+    pub is_dir: bool,
+    /// 根据文件内容推测的 MIME 类型
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// 最近一次检查时文件是否仍然存在
+    #[serde(default = "default_true")]
+    pub exists: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// 统计目录大小时最多遍历的条目数，避免复制超大目录时长时间阻塞
+const MAX_DIR_WALK_ENTRIES: usize = 100_000;
+
+impl FileItem {
+    /// 读取路径的元数据创建文件条目，路径不存在时 `exists` 为 false
+    pub fn from_path(path: &Path) -> Self {
+        let mut item = FileItem {
+            path: path.to_path_buf(),
+            size: 0,
+            modified: Utc::now(),
+            is_dir: false,
+            mime_type: None,
+            exists: false,
+        };
+        item.refresh();
+        item
+    }
+    
+    /// 重新读取元数据，返回是否有变化
+    pub fn refresh(&mut self) -> bool {
+        let (size, modified, is_dir, mime_type, exists) = match std::fs::metadata(&self.path) {
+            Ok(meta) if meta.is_dir() => (
+                Self::dir_size(&self.path),
+                meta.modified().map(DateTime::<Utc>::from).unwrap_or(self.modified),
+                true,
+                Some("inode/directory".to_string()),
+                true,
+            ),
+            Ok(meta) => (
+                meta.len(),
+                meta.modified().map(DateTime::<Utc>::from).unwrap_or(self.modified),
+                false,
+                Self::guess_mime_type(&self.path),
+                true,
+            ),
+            // 文件已被移动或删除，保留上次记录的信息
+            Err(_) => (self.size, self.modified, self.is_dir, self.mime_type.clone(), false),
+        };
+        
+        let changed = size != self.size
+            || modified != self.modified
+            || is_dir != self.is_dir
+            || mime_type != self.mime_type
+            || exists != self.exists;
+        
+        self.size = size;
+        self.modified = modified;
+        self.is_dir = is_dir;
+        self.mime_type = mime_type;
+        self.exists = exists;
+        
+        changed
+    }
+    
+    fn dir_size(path: &Path) -> u64 {
+        let mut total = 0;
+        let mut visited = 0;
+        let mut pending = vec![path.to_path_buf()];
+        
+        while let Some(dir) = pending.pop() {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            
+            for entry in entries.flatten() {
+                visited += 1;
+                if visited > MAX_DIR_WALK_ENTRIES {
+                    warn!("Directory {} too large, size is truncated", path.display());
+                    return total;
+                }
+                
+                // 不跟随符号链接，避免循环
+                match entry.path().symlink_metadata() {
+                    Ok(meta) if meta.is_dir() => pending.push(entry.path()),
+                    Ok(meta) => total += meta.len(),
+                    Err(_) => {}
+                }
+            }
+        }
+        
+        total
+    }
+    
+    fn guess_mime_type(path: &Path) -> Option<String> {
+        if let Ok(Some(kind)) = infer::get_from_path(path) {
+            return Some(kind.mime_type().to_string());
+        }
+        
+        // 无法识别的文件头：前 8KB 为合法 UTF-8 时视为文本
+        use std::io::Read;
+        let mut head = Vec::new();
+        std::fs::File::open(path).ok()?.take(8192).read_to_end(&mut head).ok()?;
+        if head.is_empty() {
+            return None;
+        }
+        
+        match std::str::from_utf8(&head) {
+            // 截断处可能正好切开一个多字节字符
+            Ok(_) => Some("text/plain".to_string()),
+            Err(e) if e.error_len().is_none() => Some("text/plain".to_string()),
+            Err(_) => Some("application/octet-stream".to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,9 +204,79 @@ pub struct ClipboardItem {
     pub preview_text: String,
     pub preview_image: Option<Vec<u8>>,
     pub metadata: HashMap<String, String>,
+    /// 同一次复制中除 `content` 之外的其他表示（HTML、RTF、图片等）
+    #[serde(default)]
+    pub alternatives: Vec<ClipboardContent>,
+    /// 到期后自动删除
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 加密保存的原始内容，此时 `content` 为隐藏敏感信息后的文本，见 `sensitive::protect_item`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_content: Option<Vec<u8>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ClipboardItem {
+    /// 全部表示，主表示在前
+    pub fn representations(&self) -> Vec<ClipboardContent> {
+        std::iter::once(&self.content)
+            .chain(self.alternatives.iter())
+            .cloned()
+            .collect()
+    }
+    
+    /// 该条目包含的全部格式
+    pub fn formats(&self) -> Vec<ClipboardFormat> {
+        std::iter::once(&self.content)
+            .chain(self.alternatives.iter())
+            .map(ClipboardFormat::of)
+            .collect()
+    }
+    
+    /// 指定格式的表示
+    pub fn representation(&self, format: &ClipboardFormat) -> Option<&ClipboardContent> {
+        std::iter::once(&self.content)
+            .chain(self.alternatives.iter())
+            .find(|content| ClipboardFormat::of(content) == *format)
+    }
+}
+
+#[cfg(test)]
+impl ClipboardItem {
+    /// 测试用的条目，除内容和预览外均为默认值
+    pub(crate) fn test_item(content: ClipboardContent) -> Self {
+        ClipboardItem {
+            id: Uuid::new_v4(),
+            preview_text: Database::search_text(std::slice::from_ref(&content)),
+            content,
+            timestamp: Utc::now(),
+            tags: Vec::new(),
+            favorite: false,
+            pinned: false,
+            source_app: None,
+            source_window: None,
+            preview_image: None,
+            metadata: HashMap::new(),
+            alternatives: Vec::new(),
+            expires_at: None,
+            encrypted_content: None,
+        }
+    }
+    
+    pub(crate) fn test_text(text: &str) -> Self {
+        Self::test_item(ClipboardContent::Text(text.to_string()))
+    }
+}
+
+/// 写回剪贴板的选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CopyOptions {
+    /// 只写入纯文本
+    pub plain_text_only: bool,
+    /// 只写入指定的表示
+    pub format: Option<ClipboardFormat>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchQuery {
     pub text: Option<String>,
     pub tags: Vec<String>,
@@ -73,9 +287,38 @@ pub struct SearchQuery {
     pub pinned_only: bool,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+    /// 对 `text` 使用模糊匹配（需要启用 `SearchSettings::enable_fuzzy_search`）
+    #[serde(default)]
+    pub fuzzy: bool,
+    /// 查询语言解析出的其他条件，见 `SearchQuery::parse`
+    #[serde(default)]
+    pub expr: Option<QueryExpr>,
+    /// 对条目完整文本进行正则匹配
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub sort: SortMode,
+    /// 用户输入的原始查询，记录搜索历史时使用
+    #[serde(default)]
+    pub input: Option<String>,
+    /// 捕获时识别出的文本类型，满足其一即可
+    #[serde(default)]
+    pub kinds: Vec<ContentKind>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// 结果排序方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortMode {
+    /// 按相关度，没有搜索文本时按时间
+    #[default]
+    Relevance,
+    /// 按时间
+    Recent,
+    /// 综合使用次数与最后使用时间，见 `SearchSettings::frecency_half_life_hours`
+    Frecency,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContentType {
     Text,
     Image,
@@ -85,6 +328,41 @@ pub enum ContentType {
     Custom,
 }
 
+impl ContentType {
+    pub fn of(content: &ClipboardContent) -> Self {
+        match content {
+            ClipboardContent::Text(_) => ContentType::Text,
+            ClipboardContent::Image(_) => ContentType::Image,
+            ClipboardContent::FileList(_) => ContentType::File,
+            ClipboardContent::Html(_) => ContentType::Html,
+            ClipboardContent::RichText(_) => ContentType::RichText,
+            ClipboardContent::Custom(..) => ContentType::Custom,
+        }
+    }
+}
+
+/// 重复内容的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicatePolicy {
+    /// 忽略新内容
+    #[default]
+    Ignore,
+    /// 将已有条目移到最前
+    MoveToTop,
+    /// 另存为新条目
+    KeepBoth,
+}
+
+/// `Database::save_item` 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveResult {
+    Inserted,
+    /// 与已有条目重复，已忽略
+    Ignored(Uuid),
+    /// 与已有条目重复，已有条目被移到最前
+    MovedToTop(Uuid),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     pub max_items: u32,
@@ -97,10 +375,28 @@ pub struct AppSettings {
     pub save_html: bool,
     pub auto_cleanup: bool,
     pub startup_delay_ms: u32,
+    #[serde(default)]
+    pub duplicate_policy: DuplicatePolicy,
+    /// 判定重复的时间窗口（秒），0 表示不限时间
+    #[serde(default = "default_ignore_duplicate_interval")]
+    pub ignore_duplicate_interval: u32,
     pub database_path: String,
     pub cache_path: String,
     pub hotkeys: HotkeyConfig,
     pub ui: UiConfig,
+    #[serde(default)]
+    pub search: SearchSettings,
+    /// 自动标签规则，按顺序对每条新条目执行
+    #[serde(default)]
+    pub auto_tag_rules: Vec<TagRule>,
+    #[serde(default)]
+    pub sensitive: SensitiveSettings,
+    #[serde(default)]
+    pub capture_policy: CapturePolicySettings,
+}
+
+fn default_ignore_duplicate_interval() -> u32 {
+    5
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -123,6 +419,141 @@ pub struct UiConfig {
     pub font_size: u32,
 }
 
+/// 搜索设置，对应 appsettings.json 的 Search 部分
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchSettings {
+    pub enable_fuzzy_search: bool,
+    /// 模糊匹配的最低得分（0 到 1）
+    pub fuzzy_threshold: f64,
+    pub search_in_tags: bool,
+    pub search_in_source: bool,
+    pub search_in_metadata: bool,
+    /// 综合排序中使用次数的半衰期（小时），越小越偏向最近使用的条目
+    pub frecency_half_life_hours: f64,
+    pub save_search_history: bool,
+    /// 保留的不同查询数量
+    pub max_search_history: u32,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            enable_fuzzy_search: true,
+            fuzzy_threshold: 0.7,
+            search_in_tags: true,
+            search_in_source: true,
+            search_in_metadata: true,
+            frecency_half_life_hours: 72.0,
+            save_search_history: true,
+            max_search_history: 100,
+        }
+    }
+}
+
+/// 搜索历史中的一条查询
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHistoryEntry {
+    pub query: String,
+    /// 最后一次搜索的时间
+    pub timestamp: DateTime<Utc>,
+    /// 最后一次搜索的结果数
+    pub result_count: u32,
+    pub use_count: u32,
+}
+
+/// 保存的搜索，作为随内容实时更新的集合使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub name: String,
+    pub query: SearchQuery,
+    /// 有新条目符合查询时发送 `ClipboardEvent::SavedSearchMatched`
+    pub subscribed: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SavedSearch {
+    pub fn new(name: impl Into<String>, query: SearchQuery) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            query,
+            subscribed: true,
+            created_at: Utc::now(),
+        }
+    }
+    
+    /// 执行时使用的查询
+    ///
+    /// 重新解析 `input`，使 `after:this-week` 等相对日期按当前时间计算；
+    /// 没有 `input` 或无法解析时使用保存的查询。
+    pub fn current_query(&self) -> SearchQuery {
+        match self.query.input.as_deref().map(SearchQuery::parse) {
+            Some(Ok(parsed)) => SearchQuery {
+                text: parsed.text,
+                expr: parsed.expr,
+                ..self.query.clone()
+            },
+            _ => self.query.clone(),
+        }
+    }
+}
+
+/// 手动整理的集合，条目按顺序排列，同一条目可以属于多个集合
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub item_count: u32,
+}
+
+impl Collection {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            created_at: Utc::now(),
+            item_count: 0,
+        }
+    }
+}
+
+/// 按相关度排序的搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub item: ClipboardItem,
+    /// 相关度，越大越相关
+    pub score: f64,
+    /// 命中位置附近的文本，命中部分以 `<mark>` 标出
+    pub snippet: String,
+    /// 模糊匹配时 `preview_text` 中命中字符的位置（按字符计）
+    #[serde(default)]
+    pub positions: Vec<usize>,
+}
+
+/// 捕获的暂停状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureState {
+    pub paused: bool,
+    /// 自动恢复的时间，`None` 表示一直暂停到手动恢复
+    pub resume_at: Option<DateTime<Utc>>,
+}
+
+// 到达恢复时间时自动恢复捕获，返回当前是否仍处于暂停
+fn resume_capture_if_due(capture_state: &Mutex<CaptureState>, event_tx: &Sender<ClipboardEvent>) -> bool {
+    let mut state = capture_state.lock();
+    if state.paused && state.resume_at.is_some_and(|resume_at| resume_at <= Utc::now()) {
+        *state = CaptureState::default();
+        info!("Clipboard capture resumed");
+        let _ = event_tx.send(ClipboardEvent::CaptureStateChanged(*state));
+    }
+    
+    state.paused
+}
+
 #[derive(Debug, Clone)]
 pub enum ClipboardEvent {
     ItemAdded(ClipboardItem),
@@ -130,22 +561,40 @@ pub enum ClipboardEvent {
     ItemRemoved(Uuid),
     SettingsChanged(AppSettings),
     HotkeyPressed(String),
+    /// 新条目符合已订阅的保存搜索
+    SavedSearchMatched {
+        search_id: Uuid,
+        name: String,
+        item_id: Uuid,
+    },
+    /// 捕获暂停或恢复
+    CaptureStateChanged(CaptureState),
 }
 
 pub struct ClipboardCore {
     settings: Arc<RwLock<AppSettings>>,
     database: Arc<Database>,
-    monitor: Option<ClipboardMonitor>,
+    backend: Arc<dyn ClipboardBackend>,
+    monitor: Option<ClipboardMonitor<dyn ClipboardBackend>>,
+    capture_state: Arc<Mutex<CaptureState>>,
     event_tx: Sender<ClipboardEvent>,
     event_rx: Receiver<ClipboardEvent>,
 }
 
 impl ClipboardCore {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let (event_tx, event_rx) = crossbeam_channel::unbounded();
-        
         // 加载设置
         let settings = Self::load_settings()?;
+        
+        Self::with_backend(backend::default_backend()?, settings)
+    }
+    
+    /// 使用指定的剪贴板后端和设置创建核心，不读写配置文件
+    pub fn with_backend(
+        backend: Arc<dyn ClipboardBackend>,
+        settings: AppSettings,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (event_tx, event_rx) = crossbeam_channel::unbounded();
         let settings = Arc::new(RwLock::new(settings));
         
         // 初始化数据库
@@ -156,7 +605,9 @@ impl ClipboardCore {
         Ok(Self {
             settings,
             database,
+            backend,
             monitor: None,
+            capture_state: Arc::new(Mutex::new(CaptureState::default())),
             event_tx,
             event_rx,
         })
@@ -167,8 +618,10 @@ impl ClipboardCore {
         
         // 启动监控器
         let monitor = ClipboardMonitor::new(
+            self.backend.clone(),
             self.settings.clone(),
             self.database.clone(),
+            self.capture_state.clone(),
             self.event_tx.clone(),
         )?;
         
@@ -197,12 +650,67 @@ impl ClipboardCore {
         self.database.get_recent_items(limit)
     }
     
+    pub fn get_recent_items_sorted(
+        &self,
+        limit: u32,
+        sort: SortMode,
+    ) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
+        let search = self.settings.read().search.clone();
+        self.database.get_recent_items_sorted(limit, sort, &search)
+    }
+    
     pub fn search_items(&self, query: SearchQuery) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
-        self.database.search_items(query)
+        let search = self.settings.read().search.clone();
+        let items = self.database.search_items(query.clone(), &search)?;
+        self.record_search(&query, items.len(), &search);
+        Ok(items)
+    }
+    
+    pub fn search_ranked(&self, query: SearchQuery) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let search = self.settings.read().search.clone();
+        let results = self.database.search_ranked(query.clone(), &search)?;
+        self.record_search(&query, results.len(), &search);
+        Ok(results)
     }
     
-    pub fn save_item(&self, item: ClipboardItem) -> Result<(), Box<dyn std::error::Error>> {
-        self.database.save_item(item)
+    // 记录搜索历史，翻页时不重复记录
+    fn record_search(&self, query: &SearchQuery, result_count: usize, settings: &SearchSettings) {
+        if !settings.save_search_history || query.offset.unwrap_or(0) > 0 {
+            return;
+        }
+        
+        let text = query.input.as_deref().or(query.text.as_deref()).unwrap_or("").trim();
+        if text.is_empty() {
+            return;
+        }
+        
+        if let Err(e) = self.database.record_search(text, result_count, settings.max_search_history) {
+            warn!("Failed to record search history: {}", e);
+        }
+    }
+    
+    pub fn get_recent_searches(&self, limit: u32) -> Result<Vec<SearchHistoryEntry>, Box<dyn std::error::Error>> {
+        self.database.get_recent_searches(limit)
+    }
+    
+    pub fn get_frequent_searches(&self, limit: u32) -> Result<Vec<SearchHistoryEntry>, Box<dyn std::error::Error>> {
+        self.database.get_frequent_searches(limit)
+    }
+    
+    pub fn complete_search(&self, prefix: &str, limit: u32) -> Result<Vec<SearchHistoryEntry>, Box<dyn std::error::Error>> {
+        self.database.complete_search(prefix, limit)
+    }
+    
+    pub fn clear_search_history(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.database.clear_search_history()
+    }
+    
+    pub fn save_item(&self, item: ClipboardItem) -> Result<SaveResult, Box<dyn std::error::Error>> {
+        let (policy, interval) = {
+            let settings = self.settings.read();
+            (settings.duplicate_policy, settings.ignore_duplicate_interval)
+        };
+        self.database.save_item(item, policy, interval)
     }
     
     pub fn update_item(&self, item: ClipboardItem) -> Result<(), Box<dyn std::error::Error>> {
@@ -217,6 +725,20 @@ impl ClipboardCore {
         self.database.get_item(id)
     }
     
+    /// 读取条目并解密加密保存的原始内容，不会修改数据库
+    pub fn reveal_item(&self, id: Uuid) -> Result<ClipboardItem, Box<dyn std::error::Error>> {
+        let mut item = self.database.get_item(id)?.ok_or("条目不存在")?;
+        
+        if let Some(data) = item.encrypted_content.take() {
+            let key = self.settings.read().sensitive.encryption_key.clone();
+            let mut contents = sensitive::decrypt_contents(&data, &key)?.into_iter();
+            item.content = contents.next().ok_or("加密内容为空")?;
+            item.alternatives = contents.collect();
+        }
+        
+        Ok(item)
+    }
+    
     pub fn set_favorite(&self, id: Uuid, favorite: bool) -> Result<(), Box<dyn std::error::Error>> {
         self.database.set_favorite(id, favorite)
     }
@@ -233,10 +755,100 @@ impl ClipboardCore {
         self.database.remove_tags(id, tags)
     }
     
+    /// 全部标签及使用数量，`/` 分隔的上级标签也会列出
+    pub fn get_tags(&self) -> Result<Vec<TagInfo>, Box<dyn std::error::Error>> {
+        self.database.get_tags()
+    }
+    
+    /// 重命名标签及其子标签，返回修改的条目数
+    pub fn rename_tag(&self, old: &str, new: &str) -> Result<u32, Box<dyn std::error::Error>> {
+        let changed = self.database.rename_tag(old, new)?;
+        info!("Renamed tag {} to {} on {} items", old, new, changed);
+        Ok(changed)
+    }
+    
+    /// 将多个标签合并为一个，返回修改的条目数
+    pub fn merge_tags(&self, sources: &[String], target: &str) -> Result<u32, Box<dyn std::error::Error>> {
+        let changed = self.database.merge_tags(sources, target)?;
+        info!("Merged {} tags into {} on {} items", sources.len(), target, changed);
+        Ok(changed)
+    }
+    
     pub fn get_statistics(&self) -> Result<Statistics, Box<dyn std::error::Error>> {
         self.database.get_statistics()
     }
     
+    /// 将历史条目写回系统剪贴板
+    pub fn copy_to_clipboard(&self, id: Uuid, options: CopyOptions) -> Result<(), Box<dyn std::error::Error>> {
+        let item = self.reveal_item(id)?;
+        
+        let mut contents = if let Some(format) = &options.format {
+            vec![item.representation(format).cloned().ok_or("条目不包含所选格式")?]
+        } else if options.plain_text_only {
+            vec![ClipboardContent::Text(Self::plain_text(&item).ok_or("条目没有纯文本内容")?)]
+        } else {
+            item.representations()
+        };
+        
+        // 附带标记，监控器收到变更时将原条目移到最前而不是重复保存
+        contents.push(ClipboardContent::Custom(
+            SELF_WRITE_FORMAT.to_string(),
+            id.to_string().into_bytes(),
+        ));
+        
+        let source_url = item.metadata.get("source_url").map(String::as_str);
+        self.backend.write_with_source(&contents, source_url)?;
+        
+        self.database.record_access(id)?;
+        Ok(())
+    }
+    
+    fn plain_text(item: &ClipboardItem) -> Option<String> {
+        if let Some(ClipboardContent::Text(text)) = item.representation(&ClipboardFormat::Text) {
+            return Some(text.clone());
+        }
+        
+        item.representations().into_iter().find_map(|content| match content {
+            ClipboardContent::Html(html) => Some(html::html_to_text(&html)),
+            ClipboardContent::FileList(files) => Some(
+                files.iter()
+                    .map(|f| f.path.to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            _ => None,
+        })
+    }
+    
+    /// 重新检查所有文件列表条目，标记已被移动或删除的文件，返回含缺失文件的条目数
+    pub fn refresh_file_items(&self) -> Result<u32, Box<dyn std::error::Error>> {
+        let mut missing = 0;
+        
+        for mut item in self.database.get_items_by_type("file")? {
+            let mut changed = false;
+            let mut has_missing = false;
+            
+            if let ClipboardContent::FileList(files) = &mut item.content {
+                for file in files.iter_mut() {
+                    changed |= file.refresh();
+                    has_missing |= !file.exists;
+                }
+            }
+            
+            if has_missing {
+                missing += 1;
+            }
+            
+            if changed {
+                self.database.update_item(item.clone())?;
+                let _ = self.event_tx.send(ClipboardEvent::ItemUpdated(item));
+            }
+        }
+        
+        info!("Refreshed file items, {} with missing files", missing);
+        Ok(missing)
+    }
+    
     pub fn cleanup_old_items(&self) -> Result<u32, Box<dyn std::error::Error>> {
         let settings = self.settings.read();
         let deleted = self.database.cleanup_old_items(settings.keep_days)?;
@@ -253,11 +865,216 @@ impl ClipboardCore {
         self.database.import_items(path)
     }
     
+    /// 对已有的全部条目重新执行自动标签规则，返回修改的条目数
+    pub fn apply_tag_rules(&self) -> Result<u32, Box<dyn std::error::Error>> {
+        let rules = self.settings.read().auto_tag_rules.clone();
+        let changed = self.database.apply_tag_rules(&rules)?;
+        
+        info!("Applied tag rules to {} items", changed);
+        Ok(changed)
+    }
+    
+    /// 保存搜索，名称不能与已有的重复
+    pub fn create_saved_search(
+        &self,
+        name: &str,
+        query: SearchQuery,
+    ) -> Result<SavedSearch, Box<dyn std::error::Error>> {
+        let search = SavedSearch::new(name.trim(), query);
+        self.database.insert_saved_search(&search)?;
+        Ok(search)
+    }
+    
+    /// 修改名称、查询或订阅状态
+    pub fn update_saved_search(&self, search: SavedSearch) -> Result<(), Box<dyn std::error::Error>> {
+        let search = SavedSearch { name: search.name.trim().to_string(), ..search };
+        self.database.update_saved_search(&search)
+    }
+    
+    pub fn delete_saved_search(&self, id: Uuid) -> Result<bool, Box<dyn std::error::Error>> {
+        self.database.delete_saved_search(id)
+    }
+    
+    pub fn get_saved_searches(&self) -> Result<Vec<SavedSearch>, Box<dyn std::error::Error>> {
+        self.database.get_saved_searches()
+    }
+    
+    /// 保存搜索当前包含的条目数量
+    pub fn count_saved_search(&self, id: Uuid) -> Result<u32, Box<dyn std::error::Error>> {
+        let search = self.database.get_saved_search(id)?.ok_or("保存的搜索不存在")?;
+        let settings = self.settings.read().search.clone();
+        self.database.count_items(&search.current_query(), &settings)
+    }
+    
+    /// 保存搜索当前包含的条目
+    pub fn get_saved_search_items(&self, id: Uuid) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let search = self.database.get_saved_search(id)?.ok_or("保存的搜索不存在")?;
+        let settings = self.settings.read().search.clone();
+        self.database.search_ranked(search.current_query(), &settings)
+    }
+    
+    /// 将保存的搜索导出为 JSON 文件
+    pub fn export_saved_searches(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let searches = self.database.get_saved_searches()?;
+        std::fs::write(path, serde_json::to_string_pretty(&searches)?)?;
+        Ok(())
+    }
+    
+    /// 从 JSON 文件导入保存的搜索，跳过已存在的 id 或名称，返回导入数量
+    pub fn import_saved_searches(&self, path: &Path) -> Result<u32, Box<dyn std::error::Error>> {
+        let searches: Vec<SavedSearch> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let existing = self.database.get_saved_searches()?;
+        
+        let mut imported = 0;
+        for search in searches {
+            let duplicate = existing.iter().any(|other| {
+                other.id == search.id || other.name.eq_ignore_ascii_case(&search.name)
+            });
+            if duplicate {
+                continue;
+            }
+            
+            self.database.insert_saved_search(&search)?;
+            imported += 1;
+        }
+        
+        info!("Imported {} saved searches", imported);
+        Ok(imported)
+    }
+    
+    /// 新建集合，名称不能与已有的重复
+    pub fn create_collection(&self, name: &str) -> Result<Collection, Box<dyn std::error::Error>> {
+        let collection = Collection::new(name.trim());
+        self.database.insert_collection(&collection)?;
+        Ok(collection)
+    }
+    
+    pub fn rename_collection(&self, id: Uuid, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.database.rename_collection(id, name.trim())
+    }
+    
+    /// 删除集合，集合中的条目保留
+    pub fn delete_collection(&self, id: Uuid) -> Result<bool, Box<dyn std::error::Error>> {
+        self.database.delete_collection(id)
+    }
+    
+    pub fn get_collections(&self) -> Result<Vec<Collection>, Box<dyn std::error::Error>> {
+        self.database.get_collections()
+    }
+    
+    pub fn get_collection_items(&self, id: Uuid) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
+        self.database.get_collection_items(id)
+    }
+    
+    /// 条目所属的集合
+    pub fn get_item_collections(&self, item_id: Uuid) -> Result<Vec<Collection>, Box<dyn std::error::Error>> {
+        self.database.get_item_collections(item_id)
+    }
+    
+    /// 将条目加入集合，`position` 为空时放在末尾
+    pub fn add_to_collection(
+        &self,
+        collection_id: Uuid,
+        item_id: Uuid,
+        position: Option<u32>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        self.database.add_to_collection(collection_id, item_id, position)
+    }
+    
+    pub fn remove_from_collection(&self, collection_id: Uuid, item_id: Uuid) -> Result<bool, Box<dyn std::error::Error>> {
+        self.database.remove_from_collection(collection_id, item_id)
+    }
+    
+    /// 调整条目在集合中的位置
+    pub fn move_in_collection(
+        &self,
+        collection_id: Uuid,
+        item_id: Uuid,
+        position: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.database.move_in_collection(collection_id, item_id, position)
+    }
+    
+    /// 按给定顺序重新排列集合，`item_ids` 必须恰好包含集合中的全部条目
+    pub fn reorder_collection(&self, collection_id: Uuid, item_ids: &[Uuid]) -> Result<(), Box<dyn std::error::Error>> {
+        self.database.reorder_collection(collection_id, item_ids)
+    }
+    
+    /// 将条目从一个集合移到另一个集合
+    pub fn move_to_collection(
+        &self,
+        item_id: Uuid,
+        from: Uuid,
+        to: Uuid,
+        position: Option<u32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.database.move_to_collection(item_id, from, to, position)
+    }
+    
+    /// 将条目复制到另一个集合，原集合中保留，条目本身不会重复
+    pub fn copy_to_collection(
+        &self,
+        item_id: Uuid,
+        to: Uuid,
+        position: Option<u32>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        self.database.add_to_collection(to, item_id, position)
+    }
+    
+    /// 暂停捕获，期间的剪贴板变更直接丢弃；`duration` 为 `None` 时一直暂停到 `resume_capture`
+    pub fn pause_capture(&self, duration: Option<Duration>) -> Result<CaptureState, Box<dyn std::error::Error>> {
+        let resume_at = match duration {
+            Some(duration) => Some(Utc::now() + chrono::Duration::from_std(duration)?),
+            None => None,
+        };
+        
+        let state = CaptureState { paused: true, resume_at };
+        *self.capture_state.lock() = state;
+        
+        match resume_at {
+            Some(resume_at) => info!("Clipboard capture paused until {}", resume_at),
+            None => info!("Clipboard capture paused"),
+        }
+        let _ = self.event_tx.send(ClipboardEvent::CaptureStateChanged(state));
+        
+        Ok(state)
+    }
+    
+    /// 恢复捕获，未暂停时不做任何事
+    pub fn resume_capture(&self) {
+        let was_paused = std::mem::take(&mut *self.capture_state.lock()).paused;
+        if was_paused {
+            info!("Clipboard capture resumed");
+            let _ = self.event_tx.send(ClipboardEvent::CaptureStateChanged(CaptureState::default()));
+        }
+    }
+    
+    pub fn capture_state(&self) -> CaptureState {
+        resume_capture_if_due(&self.capture_state, &self.event_tx);
+        *self.capture_state.lock()
+    }
+    
     pub fn get_settings(&self) -> AppSettings {
         self.settings.read().clone()
     }
     
-    pub fn update_settings(&self, settings: AppSettings) -> Result<(), Box<dyn std::error::Error>> {
+    /// 更新设置，`sensitive.encryption_key` 为空时保留当前密钥
+    pub fn update_settings(&self, mut settings: AppSettings) -> Result<(), Box<dyn std::error::Error>> {
+        let current_key = self.settings.read().sensitive.encryption_key.clone();
+        if settings.sensitive.encryption_key.is_empty() {
+            settings.sensitive.encryption_key = current_key.clone();
+        }
+        
+        for rule in &settings.auto_tag_rules {
+            rule.validate()?;
+        }
+        settings.sensitive.validate()?;
+        settings.capture_policy.validate()?;
+        
+        if settings.sensitive.encryption_key != current_key {
+            keystore::store_encryption_key(&settings.sensitive.encryption_key)?;
+        }
+        
         *self.settings.write() = settings.clone();
         Self::save_settings(&settings)?;
         
@@ -279,14 +1096,56 @@ impl ClipboardCore {
         
         let config_file = config_dir.join("config.json");
         
+        // appsettings.json 是首次运行时写入 config.json 的初始值，
+        // 之后只补充 config.json 中缺少的键
+        let mut defaults = Self::default_settings();
+        if let Some(json) = appsettings::load() {
+            appsettings::apply(&mut defaults, &json);
+        }
+        
         if config_file.exists() {
             let content = std::fs::read_to_string(config_file)?;
-            Ok(serde_json::from_str(&content)?)
+            let mut merged = serde_json::to_value(&defaults)?;
+            appsettings::merge(&mut merged, serde_json::from_str(&content)?);
+            let mut settings: AppSettings = serde_json::from_value(merged)?;
+            Self::load_encryption_key(&mut settings);
+            
+            // 无效的规则保留在设置中但不会生效
+            for rule in &settings.auto_tag_rules {
+                if let Err(e) = rule.validate() {
+                    warn!("Ignoring invalid tag rule: {}", e);
+                }
+            }
+            if let Err(e) = settings.sensitive.validate() {
+                warn!("Invalid sensitive data settings: {}", e);
+            }
+            if let Err(e) = settings.capture_policy.validate() {
+                warn!("Ignoring invalid capture policy: {}", e);
+            }
+            
+            Ok(settings)
         } else {
-            let settings = Self::default_settings();
-            let content = serde_json::to_string_pretty(&settings)?;
+            let content = serde_json::to_string_pretty(&defaults)?;
             std::fs::write(config_file, content)?;
-            Ok(settings)
+            Ok(defaults)
+        }
+    }
+    
+    // 加密密钥保存在系统凭据存储中，旧版本写入 config.json 的密钥移入凭据存储后从文件中删除
+    fn load_encryption_key(settings: &mut AppSettings) {
+        if settings.sensitive.encryption_key.is_empty() {
+            match keystore::load_encryption_key() {
+                Ok(key) => settings.sensitive.encryption_key = key.unwrap_or_default(),
+                Err(e) => warn!("{}", e),
+            }
+            return;
+        }
+        
+        let moved = keystore::store_encryption_key(&settings.sensitive.encryption_key)
+            .map_err(|e| e.into())
+            .and_then(|_| Self::save_settings(settings));
+        if let Err(e) = moved {
+            warn!("Failed to move the encryption key out of config.json: {}", e);
         }
     }
     
@@ -320,6 +1179,8 @@ impl ClipboardCore {
             save_html: true,
             auto_cleanup: true,
             startup_delay_ms: 1000,
+            duplicate_policy: DuplicatePolicy::Ignore,
+            ignore_duplicate_interval: default_ignore_duplicate_interval(),
             database_path: format!("{}\\data\\clipboard.db", config_dir),
             cache_path: format!("{}\\cache", config_dir),
             hotkeys: HotkeyConfig {
@@ -338,6 +1199,10 @@ impl ClipboardCore {
                 thumbnail_size: 64,
                 font_size: 14,
             },
+            search: SearchSettings::default(),
+            auto_tag_rules: Vec::new(),
+            sensitive: SensitiveSettings::default(),
+            capture_policy: CapturePolicySettings::default(),
         }
     }
 }
@@ -365,7 +1230,15 @@ pub enum ExportFormat {
 }
 
 pub struct Database {
-    conn: rusqlite::Connection,
+    conn: Mutex<rusqlite::Connection>,
+}
+
+/// 全文索引部分的 WHERE 条件，`ranked` 表示包含 MATCH 条件
+#[derive(Default)]
+struct FtsFilter {
+    conditions: Vec<String>,
+    values: Vec<rusqlite::types::Value>,
+    ranked: bool,
 }
 
 impl Database {
@@ -374,126 +1247,70 @@ impl Database {
             .ok_or("Invalid database path")?;
         std::fs::create_dir_all(parent)?;
         
-        let conn = rusqlite::Connection::open(path)?;
+        let mut conn = rusqlite::Connection::open(path)?;
         
         // 启用优化
-        conn.pragma_update(None, "journal_mode", &"WAL")?;
-        conn.pragma_update(None, "synchronous", &"NORMAL")?;
-        conn.pragma_update(None, "foreign_keys", &"ON")?;
-        conn.pragma_update(None, "cache_size", &"-2000")?; // 2MB cache
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        conn.pragma_update(None, "cache_size", "-2000")?; // 2MB cache
+        // INSERT OR REPLACE 删除旧行时也要触发全文索引的删除触发器
+        conn.pragma_update(None, "recursive_triggers", "ON")?;
         
-        // 创建表
-        Self::create_tables(&conn)?;
+        regexp::register(&conn)?;
+        frecency::register(&conn)?;
         
-        // 创建索引
-        Self::create_indexes(&conn)?;
+        // 创建或升级表结构
+        migrations::migrate(&mut conn, Path::new(path))?;
         
-        Ok(Self { conn })
+        Ok(Self { conn: Mutex::new(conn) })
     }
     
-    fn create_tables(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
-        conn.execute_batch(
-            r#"
-            -- 主表
-            CREATE TABLE IF NOT EXISTS clipboard_items (
-                id TEXT PRIMARY KEY,
-                content_type TEXT NOT NULL,
-                content_data BLOB,
-                content_json TEXT,
-                timestamp INTEGER NOT NULL,
-                tags_json TEXT DEFAULT '[]',
-                favorite INTEGER DEFAULT 0,
-                pinned INTEGER DEFAULT 0,
-                source_app TEXT,
-                source_window TEXT,
-                preview_text TEXT,
-                preview_image BLOB,
-                metadata_json TEXT DEFAULT '{}',
-                created_at INTEGER DEFAULT (strftime('%s', 'now')),
-                updated_at INTEGER DEFAULT (strftime('%s', 'now')),
-                access_count INTEGER DEFAULT 0
-            );
-            
-            -- 标签表（用于快速搜索）
-            CREATE TABLE IF NOT EXISTS item_tags (
-                item_id TEXT NOT NULL,
-                tag TEXT NOT NULL,
-                created_at INTEGER DEFAULT (strftime('%s', 'now')),
-                PRIMARY KEY (item_id, tag),
-                FOREIGN KEY (item_id) REFERENCES clipboard_items(id) ON DELETE CASCADE
-            );
-            
-            -- 元数据表
-            CREATE TABLE IF NOT EXISTS item_metadata (
-                item_id TEXT NOT NULL,
-                key TEXT NOT NULL,
-                value TEXT NOT NULL,
-                PRIMARY KEY (item_id, key),
-                FOREIGN KEY (item_id) REFERENCES clipboard_items(id) ON DELETE CASCADE
-            );
-            
-            -- 搜索历史
-            CREATE TABLE IF NOT EXISTS search_history (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                query TEXT NOT NULL,
-                timestamp INTEGER DEFAULT (strftime('%s', 'now')),
-                result_count INTEGER DEFAULT 0
-            );
-            "#
-        )?;
-        
-        Ok(())
-    }
-    
-    fn create_indexes(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
-        conn.execute_batch(
-            r#"
-            CREATE INDEX IF NOT EXISTS idx_items_timestamp ON clipboard_items(timestamp DESC);
-            CREATE INDEX IF NOT EXISTS idx_items_favorite ON clipboard_items(favorite) WHERE favorite = 1;
-            CREATE INDEX IF NOT EXISTS idx_items_pinned ON clipboard_items(pinned) WHERE pinned = 1;
-            CREATE INDEX IF NOT EXISTS idx_items_content_type ON clipboard_items(content_type);
-            CREATE INDEX IF NOT EXISTS idx_items_preview ON clipboard_items(preview_text);
-            CREATE INDEX IF NOT EXISTS idx_items_source ON clipboard_items(source_app);
-            
-            CREATE INDEX IF NOT EXISTS idx_tags_tag ON item_tags(tag);
-            CREATE INDEX IF NOT EXISTS idx_tags_item ON item_tags(item_id);
-            
-            CREATE INDEX IF NOT EXISTS idx_metadata ON item_metadata(key, value);
-            CREATE INDEX IF NOT EXISTS idx_search_history ON search_history(timestamp DESC);
-            "#
-        )?;
-        
-        Ok(())
-    }
-    
-    pub fn save_item(&self, item: ClipboardItem) -> Result<(), Box<dyn std::error::Error>> {
-        let tx = self.conn.transaction()?;
+    /// 保存新条目，`interval` 秒内出现过相同内容时按 `policy` 处理（0 表示不限时间）
+    pub fn save_item(
+        &self,
+        item: ClipboardItem,
+        policy: DuplicatePolicy,
+        interval: u32,
+    ) -> Result<SaveResult, Box<dyn std::error::Error>> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
         
         // 检查是否已存在（基于内容哈希）
-        let content_hash = Self::calculate_content_hash(&item.content);
+        let content_hash = Self::calculate_content_hash(&item.content, item.encrypted_content.as_deref());
         
-        let exists: bool = tx.query_row(
-            "SELECT 1 FROM clipboard_items WHERE preview_text = ? AND timestamp > ?",
-            params![
-                &item.preview_text,
-                (Utc::now() - chrono::Duration::seconds(5)).timestamp()
-            ],
-            |row| row.get(0)
-        ).unwrap_or(false);
-        
-        if exists {
-            return Ok(());
+        if policy != DuplicatePolicy::KeepBoth {
+            let since = if interval == 0 {
+                i64::MIN
+            } else {
+                (Utc::now() - chrono::Duration::seconds(interval as i64)).timestamp()
+            };
+            
+            let existing: Option<String> = tx.query_row(
+                "SELECT id FROM clipboard_items WHERE content_hash = ? AND timestamp >= ?
+                 ORDER BY timestamp DESC LIMIT 1",
+                params![&content_hash, since],
+                |row| row.get(0)
+            ).optional()?;
+            
+            if let Some(existing) = existing {
+                let existing_id = Uuid::parse_str(&existing)?;
+                
+                if policy == DuplicatePolicy::Ignore {
+                    return Ok(SaveResult::Ignored(existing_id));
+                }
+                
+                tx.execute(
+                    "UPDATE clipboard_items SET timestamp = ?, updated_at = strftime('%s', 'now') WHERE id = ?",
+                    params![item.timestamp.timestamp(), existing],
+                )?;
+                tx.commit()?;
+                return Ok(SaveResult::MovedToTop(existing_id));
+            }
         }
         
         // 准备数据
-        let content_type = match item.content {
-            ClipboardContent::Text(_) => "text",
-            ClipboardContent::Image(_) => "image",
-            ClipboardContent::FileList(_) => "file",
-            ClipboardContent::Html(_) => "html",
-            ClipboardContent::RichText(_) => "richtext",
-            ClipboardContent::Custom(name, _) => &name,
-        };
+        let content_type = Self::content_type_name(&item.content);
         
         let content_json = serde_json::to_string(&item.content)?;
         let tags_json = serde_json::to_string(&item.tags)?;
@@ -504,8 +1321,9 @@ impl Database {
             r#"
             INSERT OR REPLACE INTO clipboard_items 
             (id, content_type, content_json, timestamp, tags_json, favorite, pinned, 
-             source_app, source_window, preview_text, preview_image, metadata_json)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             source_app, source_window, preview_text, preview_image, metadata_json, content_hash,
+             search_text, expires_at, encrypted_content)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             params![
                 item.id.to_string(),
@@ -520,9 +1338,153 @@ impl Database {
                 item.preview_text,
                 item.preview_image,
                 metadata_json,
+                content_hash,
+                Self::search_text(&item.representations()),
+                item.expires_at.map(|time| time.timestamp()),
+                item.encrypted_content,
             ],
         )?;
         
+        Self::save_item_details(&tx, &item)?;
+        
+        tx.commit()?;
+        Ok(SaveResult::Inserted)
+    }
+    
+    pub fn update_item(&self, item: ClipboardItem) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        let content_type = Self::content_type_name(&item.content);
+        let content_json = serde_json::to_string(&item.content)?;
+        let tags_json = serde_json::to_string(&item.tags)?;
+        let metadata_json = serde_json::to_string(&item.metadata)?;
+        let content_hash = Self::calculate_content_hash(&item.content, item.encrypted_content.as_deref());
+        
+        let updated = tx.execute(
+            r#"
+            UPDATE clipboard_items SET
+                content_type = ?, content_json = ?, timestamp = ?, tags_json = ?,
+                favorite = ?, pinned = ?, source_app = ?, source_window = ?,
+                preview_text = ?, preview_image = ?, metadata_json = ?, content_hash = ?,
+                search_text = ?, expires_at = ?, encrypted_content = ?, updated_at = strftime('%s', 'now')
+            WHERE id = ?
+            "#,
+            params![
+                content_type,
+                content_json,
+                item.timestamp.timestamp(),
+                tags_json,
+                item.favorite as i32,
+                item.pinned as i32,
+                item.source_app,
+                item.source_window,
+                item.preview_text,
+                item.preview_image,
+                metadata_json,
+                content_hash,
+                Self::search_text(&item.representations()),
+                item.expires_at.map(|time| time.timestamp()),
+                item.encrypted_content,
+                item.id.to_string(),
+            ],
+        )?;
+        
+        if updated == 0 {
+            return Err(format!("条目不存在: {}", item.id).into());
+        }
+        
+        Self::save_item_details(&tx, &item)?;
+        
+        tx.commit()?;
+        Ok(())
+    }
+    
+    pub fn get_item(&self, id: Uuid) -> Result<Option<ClipboardItem>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare("SELECT * FROM clipboard_items WHERE id = ?")?;
+        
+        let mut rows = stmt.query_map(params![id.to_string()], |row| self.row_to_item(row))?;
+        match rows.next() {
+            Some(item) => {
+                let mut item = item?;
+                Self::load_alternatives(&conn, &mut item)?;
+                Ok(Some(item))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    /// 将条目移到最前，条目不存在时返回 false
+    pub fn touch_item(&self, id: Uuid) -> Result<bool, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            "UPDATE clipboard_items SET timestamp = ?, updated_at = strftime('%s', 'now') WHERE id = ?",
+            params![Utc::now().timestamp(), id.to_string()],
+        )?;
+        
+        Ok(updated > 0)
+    }
+    
+    /// 记录一次使用（写回剪贴板）
+    pub fn record_access(&self, id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE clipboard_items SET access_count = access_count + 1, last_accessed = ? WHERE id = ?",
+            params![Utc::now().timestamp(), id.to_string()],
+        )?;
+        
+        Ok(())
+    }
+    
+    /// 按主表示类型获取条目
+    pub fn get_items_by_type(&self, content_type: &str) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            "SELECT * FROM clipboard_items WHERE content_type = ? ORDER BY timestamp DESC"
+        )?;
+        
+        let items = stmt.query_map(params![content_type], |row| self.row_to_item(row))?;
+        
+        let mut result = Vec::new();
+        for item in items {
+            let mut item = item?;
+            Self::load_alternatives(&conn, &mut item)?;
+            result.push(item);
+        }
+        
+        Ok(result)
+    }
+    
+    /// 全文索引的内容：完整文本、去掉标记的 HTML 或文件路径，取第一个非空的表示
+    pub(crate) fn search_text(contents: &[ClipboardContent]) -> String {
+        contents.iter()
+            .map(|content| match content {
+                ClipboardContent::Text(text) => text.clone(),
+                ClipboardContent::Html(html) => html::html_to_text(html),
+                ClipboardContent::FileList(files) => files.iter()
+                    .map(|f| f.path.to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            })
+            .find(|text| !text.is_empty())
+            .unwrap_or_default()
+    }
+    
+    fn content_type_name(content: &ClipboardContent) -> &str {
+        match content {
+            ClipboardContent::Text(_) => "text",
+            ClipboardContent::Image(_) => "image",
+            ClipboardContent::FileList(_) => "file",
+            ClipboardContent::Html(_) => "html",
+            ClipboardContent::RichText(_) => "richtext",
+            ClipboardContent::Custom(name, _) => name.as_str(),
+        }
+    }
+    
+    /// 写入标签、元数据和其他表示等附属表
+    fn save_item_details(tx: &rusqlite::Transaction, item: &ClipboardItem) -> Result<(), Box<dyn std::error::Error>> {
         // 更新标签表
         tx.execute("DELETE FROM item_tags WHERE item_id = ?", params![item.id.to_string()])?;
         
@@ -533,54 +1495,1157 @@ impl Database {
             )?;
         }
         
-        // 更新元数据表
-        tx.execute("DELETE FROM item_metadata WHERE item_id = ?", params![item.id.to_string()])?;
+        // 更新元数据表
+        tx.execute("DELETE FROM item_metadata WHERE item_id = ?", params![item.id.to_string()])?;
+        
+        for (key, value) in &item.metadata {
+            tx.execute(
+                "INSERT INTO item_metadata (item_id, key, value) VALUES (?, ?, ?)",
+                params![item.id.to_string(), key, value],
+            )?;
+        }
+        
+        // 更新其他表示
+        tx.execute("DELETE FROM item_representations WHERE item_id = ?", params![item.id.to_string()])?;
+        
+        for (position, content) in item.alternatives.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO item_representations (item_id, position, format, content_json) VALUES (?, ?, ?, ?)",
+                params![
+                    item.id.to_string(),
+                    position as i64,
+                    serde_json::to_string(&ClipboardFormat::of(content))?,
+                    serde_json::to_string(content)?,
+                ],
+            )?;
+        }
+        
+        Ok(())
+    }
+    
+    /// 内容哈希，加密条目的原文摘要也参与计算，隐藏后相同的不同内容不会被视为重复
+    pub(crate) fn calculate_content_hash(content: &ClipboardContent, encrypted: Option<&[u8]>) -> String {
+        use sha2::{Sha256, Digest};
+        let joined;
+        let data = match content {
+            ClipboardContent::Text(text) => text.as_bytes(),
+            ClipboardContent::Html(html) => html.as_bytes(),
+            ClipboardContent::RichText(rtf) => rtf.as_bytes(),
+            ClipboardContent::Image(img) => &img.data,
+            ClipboardContent::FileList(files) => {
+                let paths: Vec<String> = files.iter()
+                    .map(|f| f.path.to_string_lossy().to_string())
+                    .collect();
+                joined = paths.join("|");
+                joined.as_bytes()
+            }
+            ClipboardContent::Custom(_, data) => data,
+        };
+        
+        // 类型名参与哈希，避免不同类型的相同字节被视为重复
+        let mut hasher = Sha256::new();
+        hasher.update(Self::content_type_name(content).as_bytes());
+        hasher.update([0]);
+        hasher.update(data);
+        if let Some(encrypted) = encrypted {
+            // 密文带有随机数，只使用原文摘要，相同的原文才能去重
+            hasher.update(sensitive::plaintext_digest(encrypted));
+        }
+        format!("{:x}", hasher.finalize())
+    }
+    
+    pub fn get_recent_items(&self, limit: u32) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
+        self.get_recent_items_sorted(limit, SortMode::Recent, &SearchSettings::default())
+    }
+    
+    /// 按指定方式排序获取最近的条目，`Relevance` 与 `Recent` 相同
+    pub fn get_recent_items_sorted(
+        &self,
+        limit: u32,
+        sort: SortMode,
+        settings: &SearchSettings,
+    ) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        let order = Self::order_clause(sort, None, settings, &mut values);
+        values.push(i64::from(limit).into());
+        
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT * FROM clipboard_items ORDER BY {} LIMIT ?",
+            order
+        ))?;
+        
+        let items = stmt.query_map(rusqlite::params_from_iter(values), |row| self.row_to_item(row))?;
+        
+        let mut result = Vec::new();
+        for item in items {
+            let mut item = item?;
+            Self::load_alternatives(&conn, &mut item)?;
+            result.push(item);
+        }
+        
+        Ok(result)
+    }
+    
+    pub fn search_items(
+        &self,
+        query: SearchQuery,
+        settings: &SearchSettings,
+    ) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
+        let (conditions, mut values) = Self::search_conditions(&query, settings);
+        
+        let mut sql = String::from("SELECT * FROM clipboard_items");
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        
+        // LIMIT -1 表示不限制条数，只设置 offset 时需要
+        sql.push_str(" ORDER BY ");
+        sql.push_str(&Self::order_clause(query.sort, None, settings, &mut values));
+        sql.push_str(" LIMIT ? OFFSET ?");
+        values.push(query.limit.map_or(-1, i64::from).into());
+        values.push(i64::from(query.offset.unwrap_or(0)).into());
+        
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&sql)?;
+        let items = stmt.query_map(rusqlite::params_from_iter(values), |row| self.row_to_item(row))?;
+        
+        let mut result = Vec::new();
+        for item in items {
+            let mut item = item?;
+            Self::load_alternatives(&conn, &mut item)?;
+            result.push(item);
+        }
+        
+        Ok(result)
+    }
+    
+    /// 全文搜索，结果按相关度排序并附带命中片段
+    pub fn search_ranked(
+        &self,
+        query: SearchQuery,
+        settings: &SearchSettings,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        if query.fuzzy && settings.enable_fuzzy_search {
+            return self.search_fuzzy(query, settings);
+        }
+        
+        let fts = Self::fts_filter(query.text.as_deref().unwrap_or("").split_whitespace(), settings);
+        
+        // 没有搜索文本时按时间排序
+        if fts.conditions.is_empty() {
+            return Ok(self.search_items(query, settings)?
+                .into_iter()
+                .map(|item| SearchResult {
+                    snippet: item.preview_text.clone(),
+                    item,
+                    score: 0.0,
+                    positions: Vec::new(),
+                })
+                .collect());
+        }
+        
+        let (other_conditions, other_values) = Self::search_conditions(
+            &SearchQuery { text: None, ..query.clone() },
+            settings,
+        );
+        
+        let mut conditions = fts.conditions;
+        conditions.extend(other_conditions);
+        let mut values = fts.values;
+        values.extend(other_values);
+        
+        // 只有短词时无法使用 MATCH，也就没有 bm25 和 snippet
+        let (score, snippet) = if fts.ranked {
+            ("-bm25(items_fts)", "snippet(items_fts, -1, '<mark>', '</mark>', '...', 16)")
+        } else {
+            ("0.0", "clipboard_items.preview_text")
+        };
+        
+        let order = Self::order_clause(query.sort, Some("search_score"), settings, &mut values);
+        values.push(query.limit.map_or(-1, i64::from).into());
+        values.push(i64::from(query.offset.unwrap_or(0)).into());
+        
+        let sql = format!(
+            "SELECT clipboard_items.*, {} AS search_score, {} AS search_snippet
+             FROM clipboard_items JOIN items_fts ON items_fts.rowid = clipboard_items.fts_rowid
+             WHERE {}
+             ORDER BY {} LIMIT ? OFFSET ?",
+            score, snippet, conditions.join(" AND "), order
+        );
+        
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+            Ok((
+                self.row_to_item(row)?,
+                row.get::<_, f64>("search_score")?,
+                row.get::<_, String>("search_snippet")?,
+            ))
+        })?;
+        
+        let mut result = Vec::new();
+        for row in rows {
+            let (mut item, score, snippet) = row?;
+            Self::load_alternatives(&conn, &mut item)?;
+            result.push(SearchResult { item, score, snippet, positions: Vec::new() });
+        }
+        
+        Ok(result)
+    }
+    
+    /// 对预览和标签进行模糊匹配，得分不低于阈值的结果按得分排序
+    fn search_fuzzy(
+        &self,
+        query: SearchQuery,
+        settings: &SearchSettings,
+    ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        let pattern = query.text.clone().unwrap_or_default();
+        let (conditions, values) = Self::search_conditions(
+            &SearchQuery { text: None, ..query.clone() },
+            settings,
+        );
+        
+        let mut sql = String::from(
+            "SELECT id, preview_text, tags_json, timestamp, access_count, max(timestamp, coalesce(last_accessed, 0))
+             FROM clipboard_items"
+        );
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        
+        let conn = self.conn.lock();
+        let now = Utc::now().timestamp();
+        
+        // 只读取预览和标签进行打分，命中后再加载完整条目
+        let mut matches = Vec::new();
+        {
+            let mut stmt = conn.prepare(&sql)?;
+            let mut rows = stmt.query(rusqlite::params_from_iter(values))?;
+            while let Some(row) = rows.next()? {
+                let preview: String = row.get::<_, Option<String>>(1)?.unwrap_or_default();
+                let tags_json: String = row.get(2)?;
+                
+                if let Some(m) = Self::fuzzy_score(&pattern, &preview, &tags_json, settings) {
+                    let frecency = frecency::score(
+                        row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+                        now - row.get::<_, i64>(5)?,
+                        settings.frecency_half_life_hours,
+                    );
+                    matches.push((row.get::<_, String>(0)?, row.get::<_, i64>(3)?, frecency, m));
+                }
+            }
+        }
+        
+        match query.sort {
+            SortMode::Relevance => {
+                matches.sort_by(|a, b| b.3.score.total_cmp(&a.3.score).then(b.1.cmp(&a.1)))
+            }
+            SortMode::Recent => matches.sort_by_key(|m| std::cmp::Reverse(m.1)),
+            SortMode::Frecency => matches.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.cmp(&a.1))),
+        }
+        
+        let offset = query.offset.unwrap_or(0) as usize;
+        let limit = query.limit.map_or(usize::MAX, |limit| limit as usize);
+        
+        let mut stmt = conn.prepare_cached("SELECT * FROM clipboard_items WHERE id = ?")?;
+        let mut result = Vec::new();
+        for (id, _, _, m) in matches.into_iter().skip(offset).take(limit) {
+            let mut item = stmt.query_row(params![id], |row| self.row_to_item(row))?;
+            Self::load_alternatives(&conn, &mut item)?;
+            result.push(SearchResult {
+                snippet: item.preview_text.clone(),
+                item,
+                score: m.score,
+                positions: m.positions,
+            });
+        }
+        
+        Ok(result)
+    }
+    
+    /// 对预览和标签进行模糊匹配，返回得分最高的结果
+    fn fuzzy_score(
+        pattern: &str,
+        preview: &str,
+        tags_json: &str,
+        settings: &SearchSettings,
+    ) -> Option<fuzzy::FuzzyMatch> {
+        let mut best = fuzzy::fuzzy_match(pattern, preview, settings.fuzzy_threshold);
+        if settings.search_in_tags {
+            let tags: Vec<String> = serde_json::from_str(tags_json).unwrap_or_default();
+            for tag in &tags {
+                if let Some(m) = fuzzy::fuzzy_match(pattern, tag, settings.fuzzy_threshold) {
+                    if best.as_ref().is_none_or(|b| m.score > b.score) {
+                        // 标签命中时没有预览中的位置
+                        best = Some(fuzzy::FuzzyMatch { score: m.score, positions: Vec::new() });
+                    }
+                }
+            }
+        }
+        
+        best
+    }
+    
+    /// 符合查询的条目数量，忽略 `limit` 和 `offset`
+    pub fn count_items(
+        &self,
+        query: &SearchQuery,
+        settings: &SearchSettings,
+    ) -> Result<u32, Box<dyn std::error::Error>> {
+        let query = SearchQuery { limit: None, offset: None, ..query.clone() };
+        if query.fuzzy && settings.enable_fuzzy_search {
+            return Ok(self.search_fuzzy(query, settings)?.len() as u32);
+        }
+        
+        let (conditions, values) = Self::search_conditions(&query, settings);
+        let mut sql = String::from("SELECT COUNT(*) FROM clipboard_items");
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        
+        let conn = self.conn.lock();
+        let count: u32 = conn.query_row(&sql, rusqlite::params_from_iter(values), |row| row.get(0))?;
+        Ok(count)
+    }
+    
+    /// 判断条目是否符合查询
+    pub fn item_matches(
+        &self,
+        id: Uuid,
+        query: &SearchQuery,
+        settings: &SearchSettings,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let fuzzy = query.fuzzy && settings.enable_fuzzy_search;
+        let (mut conditions, mut values) = if fuzzy {
+            Self::search_conditions(&SearchQuery { text: None, ..query.clone() }, settings)
+        } else {
+            Self::search_conditions(query, settings)
+        };
+        conditions.push("id = ?".to_string());
+        values.push(id.to_string().into());
+        
+        let sql = format!(
+            "SELECT preview_text, tags_json FROM clipboard_items WHERE {}",
+            conditions.join(" AND ")
+        );
+        
+        let conn = self.conn.lock();
+        let row: Option<(Option<String>, String)> = conn
+            .query_row(&sql, rusqlite::params_from_iter(values), |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?;
+        
+        Ok(match row {
+            Some((preview, tags_json)) if fuzzy => {
+                let pattern = query.text.as_deref().unwrap_or("");
+                Self::fuzzy_score(pattern, &preview.unwrap_or_default(), &tags_json, settings).is_some()
+            }
+            Some(_) => true,
+            None => false,
+        })
+    }
+    
+    /// 排序子句，`relevance` 为相关度表达式，没有时按时间排序
+    fn order_clause(
+        sort: SortMode,
+        relevance: Option<&str>,
+        settings: &SearchSettings,
+        values: &mut Vec<rusqlite::types::Value>,
+    ) -> String {
+        match (sort, relevance) {
+            (SortMode::Relevance, Some(relevance)) => format!("{} DESC, timestamp DESC", relevance),
+            (SortMode::Relevance, None) | (SortMode::Recent, _) => "timestamp DESC".to_string(),
+            (SortMode::Frecency, _) => {
+                values.push(Utc::now().timestamp().into());
+                values.push(settings.frecency_half_life_hours.into());
+                "frecency(access_count, max(timestamp, coalesce(last_accessed, 0)), ?, ?) DESC, timestamp DESC"
+                    .to_string()
+            }
+        }
+    }
+    
+    /// 将查询条件转换为 WHERE 子句及对应的参数
+    fn search_conditions(
+        query: &SearchQuery,
+        settings: &SearchSettings,
+    ) -> (Vec<String>, Vec<rusqlite::types::Value>) {
+        let mut conditions = Vec::new();
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        
+        let fts = Self::fts_filter(query.text.as_deref().unwrap_or("").split_whitespace(), settings);
+        if !fts.conditions.is_empty() {
+            conditions.push(format!(
+                "clipboard_items.fts_rowid IN (SELECT rowid FROM items_fts WHERE {})",
+                fts.conditions.join(" AND ")
+            ));
+            values.extend(fts.values);
+        }
+        
+        // 需要包含全部标签，子标签也算在内
+        for tag in &query.tags {
+            conditions.push(Self::tag_condition(tag, &mut values));
+        }
+        
+        if let Some(from) = query.date_from {
+            conditions.push("timestamp >= ?".to_string());
+            values.push(from.timestamp().into());
+        }
+        
+        if let Some(to) = query.date_to {
+            conditions.push("timestamp <= ?".to_string());
+            values.push(to.timestamp().into());
+        }
+        
+        if !query.content_types.is_empty() {
+            let mut types = Vec::new();
+            let mut custom = false;
+            for content_type in &query.content_types {
+                match Self::content_type_column(*content_type) {
+                    Some(name) => types.push(name),
+                    None => custom = true,
+                }
+            }
+            
+            let mut alternatives = Vec::new();
+            if !types.is_empty() {
+                alternatives.push(format!("content_type IN ({})", Self::placeholders(types.len())));
+                values.extend(types.iter().map(|name| name.to_string().into()));
+            }
+            if custom {
+                // 自定义格式以格式名作为 content_type
+                alternatives.push(
+                    "content_type NOT IN ('text', 'image', 'file', 'html', 'richtext')".to_string()
+                );
+            }
+            conditions.push(format!("({})", alternatives.join(" OR ")));
+        }
+        
+        if !query.kinds.is_empty() {
+            conditions.push(format!(
+                "clipboard_items.id IN (SELECT item_id FROM item_metadata WHERE key = ? AND value IN ({}))",
+                Self::placeholders(query.kinds.len())
+            ));
+            values.push(classify::KIND_KEY.to_string().into());
+            values.extend(query.kinds.iter().map(|kind| kind.as_str().to_string().into()));
+        }
+        
+        if query.favorite_only {
+            conditions.push("favorite = 1".to_string());
+        }
+        
+        if query.pinned_only {
+            conditions.push("pinned = 1".to_string());
+        }
+        
+        if let Some(pattern) = &query.regex {
+            conditions.push("coalesce(search_text, '') REGEXP ?".to_string());
+            values.push(pattern.clone().into());
+        }
+        
+        if let Some(expr) = &query.expr {
+            conditions.push(Self::expr_condition(expr, settings, &mut values));
+        }
+        
+        (conditions, values)
+    }
+    
+    /// 带有该标签或其子标签（`work` 包含 `work/projectA`）
+    fn tag_condition(tag: &str, values: &mut Vec<rusqlite::types::Value>) -> String {
+        let tag = tags::normalize_tag(tag).unwrap_or_default();
+        let prefix = format!("{}/", tag);
+        values.push(tag.into());
+        values.push((prefix.chars().count() as i64).into());
+        values.push(prefix.into());
+        "clipboard_items.id IN (SELECT item_id FROM item_tags WHERE tag = ? OR substr(tag, 1, ?) = ?)".to_string()
+    }
+    
+    /// 将查询表达式转换为 SQL 条件，参数追加到 `values`
+    fn expr_condition(
+        expr: &QueryExpr,
+        settings: &SearchSettings,
+        values: &mut Vec<rusqlite::types::Value>,
+    ) -> String {
+        match expr {
+            QueryExpr::And(terms) | QueryExpr::Or(terms) => {
+                let op = if matches!(expr, QueryExpr::And(_)) { " AND " } else { " OR " };
+                let parts: Vec<String> = terms.iter()
+                    .map(|term| Self::expr_condition(term, settings, values))
+                    .collect();
+                if parts.is_empty() {
+                    "1".to_string()
+                } else {
+                    format!("({})", parts.join(op))
+                }
+            }
+            QueryExpr::Not(inner) => format!("NOT {}", Self::expr_condition(inner, settings, values)),
+            QueryExpr::Text(text) | QueryExpr::Phrase(text) => {
+                // 普通文本按空白拆分，短语整体匹配
+                let fts = if matches!(expr, QueryExpr::Text(_)) {
+                    Self::fts_filter(text.split_whitespace(), settings)
+                } else {
+                    Self::fts_filter(std::iter::once(text.as_str()).filter(|t| !t.is_empty()), settings)
+                };
+                if fts.conditions.is_empty() {
+                    return "1".to_string();
+                }
+                values.extend(fts.values);
+                format!(
+                    "clipboard_items.fts_rowid IN (SELECT rowid FROM items_fts WHERE {})",
+                    fts.conditions.join(" AND ")
+                )
+            }
+            QueryExpr::Regex(pattern) => {
+                values.push(pattern.clone().into());
+                "coalesce(search_text, '') REGEXP ?".to_string()
+            }
+            QueryExpr::Tag(tag) => Self::tag_condition(tag, values),
+            QueryExpr::Type(content_type) => match Self::content_type_column(*content_type) {
+                Some(name) => {
+                    values.push(name.to_string().into());
+                    "content_type = ?".to_string()
+                }
+                None => "content_type NOT IN ('text', 'image', 'file', 'html', 'richtext')".to_string(),
+            },
+            QueryExpr::Kind(kind) => {
+                values.push(classify::KIND_KEY.to_string().into());
+                values.push(kind.as_str().to_string().into());
+                "clipboard_items.id IN (SELECT item_id FROM item_metadata WHERE key = ? AND value = ?)".to_string()
+            }
+            QueryExpr::Source(source) => {
+                // 没有来源的条目在取反时也应命中，因此用 coalesce 避免 NULL
+                let pattern = format!("%{}%", Self::escape_like(source));
+                values.push(pattern.clone().into());
+                values.push(pattern.into());
+                "(coalesce(source_app, '') LIKE ? ESCAPE '\\' OR coalesce(source_window, '') LIKE ? ESCAPE '\\')"
+                    .to_string()
+            }
+            QueryExpr::After(time) => {
+                values.push(time.timestamp().into());
+                "timestamp >= ?".to_string()
+            }
+            QueryExpr::Before(time) => {
+                values.push(time.timestamp().into());
+                "timestamp < ?".to_string()
+            }
+            QueryExpr::Pinned => "pinned = 1".to_string(),
+            QueryExpr::Favorite => "favorite = 1".to_string(),
+        }
+    }
+    
+    /// 全文索引的匹配条件
+    ///
+    /// 索引使用 trigram 分词以支持中文子串匹配，不足三个字符的词无法通过
+    /// MATCH 查询，退回到对索引列的 LIKE。
+    fn fts_filter<'a>(terms: impl Iterator<Item = &'a str>, settings: &SearchSettings) -> FtsFilter {
+        let mut columns = vec!["body"];
+        if settings.search_in_tags {
+            columns.push("tags");
+        }
+        if settings.search_in_source {
+            columns.push("source");
+        }
+        if settings.search_in_metadata {
+            columns.push("metadata");
+        }
+        
+        let mut filter = FtsFilter::default();
+        let mut phrases = Vec::new();
+        
+        for term in terms {
+            if term.chars().count() >= 3 {
+                phrases.push(format!("\"{}\"", term.replace('"', "\"\"")));
+            } else {
+                let pattern = format!("%{}%", Self::escape_like(term));
+                let alternatives: Vec<String> = columns.iter()
+                    .map(|column| format!("items_fts.{} LIKE ? ESCAPE '\\'", column))
+                    .collect();
+                filter.conditions.push(format!("({})", alternatives.join(" OR ")));
+                filter.values.extend(columns.iter().map(|_| pattern.clone().into()));
+            }
+        }
+        
+        if !phrases.is_empty() {
+            filter.conditions.insert(0, "items_fts MATCH ?".to_string());
+            filter.values.insert(0, format!("{{{}}} : ({})", columns.join(" "), phrases.join(" AND ")).into());
+            filter.ranked = true;
+        }
+        
+        filter
+    }
+    
+    /// `ContentType` 对应的 content_type 列值，自定义格式返回 `None`
+    fn content_type_column(content_type: ContentType) -> Option<&'static str> {
+        match content_type {
+            ContentType::Text => Some("text"),
+            ContentType::Image => Some("image"),
+            ContentType::File => Some("file"),
+            ContentType::Html => Some("html"),
+            ContentType::RichText => Some("richtext"),
+            ContentType::Custom => None,
+        }
+    }
+    
+    fn placeholders(count: usize) -> String {
+        vec!["?"; count].join(", ")
+    }
+    
+    fn escape_like(text: &str) -> String {
+        text.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+    
+    fn load_alternatives(conn: &rusqlite::Connection, item: &mut ClipboardItem) -> Result<(), Box<dyn std::error::Error>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT content_json FROM item_representations WHERE item_id = ? ORDER BY position"
+        )?;
+        
+        let rows = stmt.query_map(params![item.id.to_string()], |row| row.get::<_, String>(0))?;
+        
+        item.alternatives.clear();
+        for content_json in rows {
+            item.alternatives.push(serde_json::from_str(&content_json?)?);
+        }
+        
+        Ok(())
+    }
+    
+    /// 记录一次搜索，只保留最近使用的 `max_history` 个不同查询
+    ///
+    /// 边输入边搜索时，几秒内连续输入的前缀会合并为最终的查询。
+    pub fn record_search(
+        &self,
+        query: &str,
+        result_count: usize,
+        max_history: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        const TYPING_WINDOW_SECS: i64 = 5;
+        
+        let now = Utc::now().timestamp();
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        let last: Option<(i64, String, i64, u32)> = tx.query_row(
+            "SELECT id, query, timestamp, use_count FROM search_history ORDER BY timestamp DESC, id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        ).optional()?;
+        if let Some((id, last_query, timestamp, use_count)) = last {
+            let typing = now - timestamp <= TYPING_WINDOW_SECS
+                && use_count == 1
+                && last_query != query
+                && (query.starts_with(last_query.as_str()) || last_query.starts_with(query));
+            if typing {
+                tx.execute("DELETE FROM search_history WHERE id = ?", params![id])?;
+            }
+        }
+        
+        // 重新插入而不是原地更新，使同一秒内的查询也能按 id 区分先后
+        let use_count: u32 = tx.query_row(
+            "SELECT use_count FROM search_history WHERE query = ?",
+            params![query],
+            |row| row.get(0),
+        ).optional()?.unwrap_or(0);
+        tx.execute("DELETE FROM search_history WHERE query = ?", params![query])?;
+        tx.execute(
+            "INSERT INTO search_history (query, timestamp, result_count, use_count) VALUES (?, ?, ?, ?)",
+            params![query, now, result_count as i64, use_count + 1],
+        )?;
+        
+        tx.execute(
+            "DELETE FROM search_history WHERE id NOT IN (
+                SELECT id FROM search_history ORDER BY timestamp DESC, id DESC LIMIT ?
+            )",
+            params![max_history],
+        )?;
+        
+        tx.commit()?;
+        Ok(())
+    }
+    
+    /// 最近使用的查询
+    pub fn get_recent_searches(&self, limit: u32) -> Result<Vec<SearchHistoryEntry>, Box<dyn std::error::Error>> {
+        self.query_search_history(
+            "SELECT * FROM search_history ORDER BY timestamp DESC, id DESC LIMIT ?",
+            params![limit],
+        )
+    }
+    
+    /// 使用次数最多的查询
+    pub fn get_frequent_searches(&self, limit: u32) -> Result<Vec<SearchHistoryEntry>, Box<dyn std::error::Error>> {
+        self.query_search_history(
+            "SELECT * FROM search_history ORDER BY use_count DESC, timestamp DESC LIMIT ?",
+            params![limit],
+        )
+    }
+    
+    /// 以 `prefix` 开头的历史查询（不区分大小写），常用的排在前面
+    pub fn complete_search(&self, prefix: &str, limit: u32) -> Result<Vec<SearchHistoryEntry>, Box<dyn std::error::Error>> {
+        self.query_search_history(
+            r#"
+            SELECT * FROM search_history
+            WHERE query LIKE ? ESCAPE '\' AND query <> ?
+            ORDER BY use_count DESC, timestamp DESC LIMIT ?
+            "#,
+            params![format!("{}%", Self::escape_like(prefix)), prefix, limit],
+        )
+    }
+    
+    pub fn clear_search_history(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        conn.execute("DELETE FROM search_history", [])?;
+        Ok(())
+    }
+    
+    fn query_search_history(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<SearchHistoryEntry>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(sql)?;
+        let entries = stmt.query_map(params, |row| {
+            Ok(SearchHistoryEntry {
+                query: row.get("query")?,
+                timestamp: DateTime::from_timestamp(row.get("timestamp")?, 0).unwrap_or_default(),
+                result_count: row.get("result_count")?,
+                use_count: row.get("use_count")?,
+            })
+        })?;
+        
+        Ok(entries.collect::<Result<_, _>>()?)
+    }
+    
+    pub fn insert_saved_search(&self, search: &SavedSearch) -> Result<(), Box<dyn std::error::Error>> {
+        if search.name.is_empty() {
+            return Err("名称不能为空".into());
+        }
+        
+        let conn = self.conn.lock();
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM saved_searches WHERE name = ?",
+            params![search.name],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(format!("已存在名为 {} 的搜索", search.name).into());
+        }
+        
+        conn.execute(
+            "INSERT INTO saved_searches (id, name, query_json, subscribed, created_at) VALUES (?, ?, ?, ?, ?)",
+            params![
+                search.id.to_string(),
+                search.name,
+                serde_json::to_string(&search.query)?,
+                search.subscribed,
+                search.created_at.timestamp(),
+            ],
+        )?;
+        
+        Ok(())
+    }
+    
+    pub fn update_saved_search(&self, search: &SavedSearch) -> Result<(), Box<dyn std::error::Error>> {
+        if search.name.is_empty() {
+            return Err("名称不能为空".into());
+        }
+        
+        let conn = self.conn.lock();
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM saved_searches WHERE name = ? AND id <> ?",
+            params![search.name, search.id.to_string()],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(format!("已存在名为 {} 的搜索", search.name).into());
+        }
+        
+        let updated = conn.execute(
+            r#"
+            UPDATE saved_searches
+            SET name = ?, query_json = ?, subscribed = ?, updated_at = strftime('%s', 'now')
+            WHERE id = ?
+            "#,
+            params![
+                search.name,
+                serde_json::to_string(&search.query)?,
+                search.subscribed,
+                search.id.to_string(),
+            ],
+        )?;
+        if updated == 0 {
+            return Err("保存的搜索不存在".into());
+        }
+        
+        Ok(())
+    }
+    
+    pub fn delete_saved_search(&self, id: Uuid) -> Result<bool, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let deleted = conn.execute("DELETE FROM saved_searches WHERE id = ?", params![id.to_string()])?;
+        Ok(deleted > 0)
+    }
+    
+    pub fn get_saved_search(&self, id: Uuid) -> Result<Option<SavedSearch>, Box<dyn std::error::Error>> {
+        Ok(self.query_saved_searches(
+            "SELECT * FROM saved_searches WHERE id = ?",
+            params![id.to_string()],
+        )?.pop())
+    }
+    
+    pub fn get_saved_searches(&self) -> Result<Vec<SavedSearch>, Box<dyn std::error::Error>> {
+        self.query_saved_searches("SELECT * FROM saved_searches ORDER BY name COLLATE NOCASE", [])
+    }
+    
+    fn query_saved_searches(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<SavedSearch>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, String>("id")?,
+                row.get::<_, String>("name")?,
+                row.get::<_, String>("query_json")?,
+                row.get::<_, bool>("subscribed")?,
+                row.get::<_, i64>("created_at")?,
+            ))
+        })?;
+        
+        let mut searches = Vec::new();
+        for row in rows {
+            let (id, name, query_json, subscribed, created_at) = row?;
+            searches.push(SavedSearch {
+                id: Uuid::parse_str(&id)?,
+                name,
+                query: serde_json::from_str(&query_json)?,
+                subscribed,
+                created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_else(Utc::now),
+            });
+        }
+        
+        Ok(searches)
+    }
+    
+    pub fn insert_collection(&self, collection: &Collection) -> Result<(), Box<dyn std::error::Error>> {
+        if collection.name.is_empty() {
+            return Err("名称不能为空".into());
+        }
+        
+        let conn = self.conn.lock();
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM collections WHERE name = ?",
+            params![collection.name],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(format!("已存在名为 {} 的集合", collection.name).into());
+        }
+        
+        conn.execute(
+            "INSERT INTO collections (id, name, created_at) VALUES (?, ?, ?)",
+            params![collection.id.to_string(), collection.name, collection.created_at.timestamp()],
+        )?;
+        
+        Ok(())
+    }
+    
+    pub fn rename_collection(&self, id: Uuid, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if name.is_empty() {
+            return Err("名称不能为空".into());
+        }
+        
+        let conn = self.conn.lock();
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM collections WHERE name = ? AND id <> ?",
+            params![name, id.to_string()],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Err(format!("已存在名为 {} 的集合", name).into());
+        }
+        
+        let updated = conn.execute(
+            "UPDATE collections SET name = ?, updated_at = strftime('%s', 'now') WHERE id = ?",
+            params![name, id.to_string()],
+        )?;
+        if updated == 0 {
+            return Err("集合不存在".into());
+        }
+        
+        Ok(())
+    }
+    
+    pub fn delete_collection(&self, id: Uuid) -> Result<bool, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let deleted = conn.execute("DELETE FROM collections WHERE id = ?", params![id.to_string()])?;
+        Ok(deleted > 0)
+    }
+    
+    pub fn get_collection(&self, id: Uuid) -> Result<Option<Collection>, Box<dyn std::error::Error>> {
+        Ok(self.query_collections(
+            "SELECT collections.*, (SELECT COUNT(*) FROM collection_items WHERE collection_id = collections.id) AS item_count
+             FROM collections WHERE id = ?",
+            params![id.to_string()],
+        )?.pop())
+    }
+    
+    pub fn get_collections(&self) -> Result<Vec<Collection>, Box<dyn std::error::Error>> {
+        self.query_collections(
+            "SELECT collections.*, (SELECT COUNT(*) FROM collection_items WHERE collection_id = collections.id) AS item_count
+             FROM collections ORDER BY name",
+            [],
+        )
+    }
+    
+    /// 条目所属的集合
+    pub fn get_item_collections(&self, item_id: Uuid) -> Result<Vec<Collection>, Box<dyn std::error::Error>> {
+        self.query_collections(
+            "SELECT collections.*, (SELECT COUNT(*) FROM collection_items WHERE collection_id = collections.id) AS item_count
+             FROM collections
+             WHERE id IN (SELECT collection_id FROM collection_items WHERE item_id = ?)
+             ORDER BY name",
+            params![item_id.to_string()],
+        )
+    }
+    
+    fn query_collections(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Collection>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, String>("id")?,
+                row.get::<_, String>("name")?,
+                row.get::<_, i64>("created_at")?,
+                row.get::<_, u32>("item_count")?,
+            ))
+        })?;
+        
+        let mut collections = Vec::new();
+        for row in rows {
+            let (id, name, created_at, item_count) = row?;
+            collections.push(Collection {
+                id: Uuid::parse_str(&id)?,
+                name,
+                created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_else(Utc::now),
+                item_count,
+            });
+        }
+        
+        Ok(collections)
+    }
+    
+    /// 集合中的条目，按位置排序
+    pub fn get_collection_items(&self, id: Uuid) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT clipboard_items.* FROM collection_items
+            JOIN clipboard_items ON clipboard_items.id = collection_items.item_id
+            WHERE collection_items.collection_id = ?
+            ORDER BY collection_items.position
+            "#
+        )?;
+        
+        let items = stmt.query_map(params![id.to_string()], |row| self.row_to_item(row))?;
+        
+        let mut result = Vec::new();
+        for item in items {
+            let mut item = item?;
+            Self::load_alternatives(&conn, &mut item)?;
+            result.push(item);
+        }
+        
+        Ok(result)
+    }
+    
+    /// 将条目加入集合，已在集合中时返回 false
+    pub fn add_to_collection(
+        &self,
+        collection_id: Uuid,
+        item_id: Uuid,
+        position: Option<u32>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        let added = Self::insert_into_collection(&tx, collection_id, item_id, position)?;
+        tx.commit()?;
+        Ok(added)
+    }
+    
+    pub fn remove_from_collection(&self, collection_id: Uuid, item_id: Uuid) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        let mut order = Self::collection_order(&tx, collection_id)?;
+        let before = order.len();
+        order.retain(|id| *id != item_id.to_string());
+        if order.len() == before {
+            return Ok(false);
+        }
+        
+        tx.execute(
+            "DELETE FROM collection_items WHERE collection_id = ? AND item_id = ?",
+            params![collection_id.to_string(), item_id.to_string()],
+        )?;
+        Self::write_collection_order(&tx, collection_id, &order)?;
+        
+        tx.commit()?;
+        Ok(true)
+    }
+    
+    /// 调整条目在集合中的位置，超出范围时放在末尾
+    pub fn move_in_collection(
+        &self,
+        collection_id: Uuid,
+        item_id: Uuid,
+        position: u32,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        let mut order = Self::collection_order(&tx, collection_id)?;
+        let current = order.iter()
+            .position(|id| *id == item_id.to_string())
+            .ok_or("条目不在集合中")?;
+        let id = order.remove(current);
+        order.insert((position as usize).min(order.len()), id);
+        Self::write_collection_order(&tx, collection_id, &order)?;
+        
+        tx.commit()?;
+        Ok(())
+    }
+    
+    /// 按给定顺序重新排列集合
+    pub fn reorder_collection(&self, collection_id: Uuid, item_ids: &[Uuid]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        let mut current = Self::collection_order(&tx, collection_id)?;
+        let order: Vec<String> = item_ids.iter().map(Uuid::to_string).collect();
+        
+        let mut sorted = order.clone();
+        sorted.sort();
+        sorted.dedup();
+        current.sort();
+        if sorted.len() != order.len() || sorted != current {
+            return Err("新的顺序必须恰好包含集合中的全部条目".into());
+        }
+        
+        Self::write_collection_order(&tx, collection_id, &order)?;
         
-        for (key, value) in &item.metadata {
-            tx.execute(
-                "INSERT INTO item_metadata (item_id, key, value) VALUES (?, ?, ?)",
-                params![item.id.to_string(), key, value],
-            )?;
+        tx.commit()?;
+        Ok(())
+    }
+    
+    /// 将条目从 `from` 移到 `to`，目标集合中已有该条目时只从原集合移除
+    pub fn move_to_collection(
+        &self,
+        item_id: Uuid,
+        from: Uuid,
+        to: Uuid,
+        position: Option<u32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if from == to {
+            return match position {
+                Some(position) => self.move_in_collection(from, item_id, position),
+                None => Ok(()),
+            };
         }
         
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        let mut order = Self::collection_order(&tx, from)?;
+        let current = order.iter()
+            .position(|id| *id == item_id.to_string())
+            .ok_or("条目不在集合中")?;
+        order.remove(current);
+        
+        tx.execute(
+            "DELETE FROM collection_items WHERE collection_id = ? AND item_id = ?",
+            params![from.to_string(), item_id.to_string()],
+        )?;
+        Self::write_collection_order(&tx, from, &order)?;
+        Self::insert_into_collection(&tx, to, item_id, position)?;
+        
         tx.commit()?;
         Ok(())
     }
     
-    fn calculate_content_hash(content: &ClipboardContent) -> String {
-        use sha2::{Sha256, Digest};
-        let data = match content {
-            ClipboardContent::Text(text) => text.as_bytes(),
-            ClipboardContent::Html(html) => html.as_bytes(),
-            ClipboardContent::RichText(rtf) => rtf.as_bytes(),
-            ClipboardContent::Image(img) => &img.data,
-            ClipboardContent::FileList(files) => {
-                let paths: Vec<String> = files.iter()
-                    .map(|f| f.path.to_string_lossy().to_string())
-                    .collect();
-                paths.join("|").as_bytes()
-            }
-            ClipboardContent::Custom(_, data) => data,
-        };
+    fn insert_into_collection(
+        conn: &rusqlite::Connection,
+        collection_id: Uuid,
+        item_id: Uuid,
+        position: Option<u32>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut order = Self::collection_order(conn, collection_id)?;
+        if order.contains(&item_id.to_string()) {
+            return Ok(false);
+        }
         
-        let mut hasher = Sha256::new();
-        hasher.update(data);
-        format!("{:x}", hasher.finalize())
+        let item_exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM clipboard_items WHERE id = ?",
+            params![item_id.to_string()],
+            |row| row.get(0),
+        )?;
+        if !item_exists {
+            return Err(format!("条目不存在: {}", item_id).into());
+        }
+        
+        let index = position.map_or(order.len(), |position| (position as usize).min(order.len()));
+        conn.execute(
+            "INSERT INTO collection_items (collection_id, item_id, position) VALUES (?, ?, ?)",
+            params![collection_id.to_string(), item_id.to_string(), index as i64],
+        )?;
+        order.insert(index, item_id.to_string());
+        Self::write_collection_order(conn, collection_id, &order)?;
+        
+        Ok(true)
     }
     
-    pub fn get_recent_items(&self, limit: u32) -> Result<Vec<ClipboardItem>, Box<dyn std::error::Error>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT * FROM clipboard_items ORDER BY timestamp DESC LIMIT ?"
+    // 集合中条目的 id，按位置排序；集合不存在时返回错误
+    fn collection_order(conn: &rusqlite::Connection, collection_id: Uuid) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM collections WHERE id = ?",
+            params![collection_id.to_string()],
+            |row| row.get(0),
         )?;
+        if !exists {
+            return Err("集合不存在".into());
+        }
         
-        let items = stmt.query_map(params![limit], |row| self.row_to_item(row))?;
-        
-        let mut result = Vec::new();
-        for item in items {
-            result.push(item?);
+        let mut stmt = conn.prepare_cached(
+            "SELECT item_id FROM collection_items WHERE collection_id = ? ORDER BY position"
+        )?;
+        let ids = stmt.query_map(params![collection_id.to_string()], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+    
+    // 按顺序重写位置，保持从 0 开始连续
+    fn write_collection_order(
+        conn: &rusqlite::Connection,
+        collection_id: Uuid,
+        order: &[String],
+    ) -> rusqlite::Result<()> {
+        let mut stmt = conn.prepare_cached(
+            "UPDATE collection_items SET position = ? WHERE collection_id = ? AND item_id = ?"
+        )?;
+        for (position, item_id) in order.iter().enumerate() {
+            stmt.execute(params![position as i64, collection_id.to_string(), item_id])?;
         }
         
-        Ok(result)
+        Ok(())
     }
     
     fn row_to_item(&self, row: &rusqlite::Row) -> rusqlite::Result<ClipboardItem> {
@@ -621,25 +2686,50 @@ impl Database {
             preview_text: row.get("preview_text")?,
             preview_image: row.get("preview_image")?,
             metadata,
+            alternatives: Vec::new(),
+            expires_at: row.get::<_, Option<i64>>("expires_at")?
+                .and_then(|time| DateTime::from_timestamp(time, 0)),
+            encrypted_content: row.get("encrypted_content")?,
         })
     }
     
+    /// 删除已到期的条目，返回被删除的条目 id
+    pub fn delete_expired_items(&self) -> Result<Vec<Uuid>, Box<dyn std::error::Error>> {
+        let conn = self.conn.lock();
+        let now = Utc::now().timestamp();
+        
+        let ids: Vec<String> = conn
+            .prepare_cached("SELECT id FROM clipboard_items WHERE expires_at IS NOT NULL AND expires_at <= ?")?
+            .query_map(params![now], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        conn.execute("DELETE FROM clipboard_items WHERE expires_at IS NOT NULL AND expires_at <= ?", params![now])?;
+        
+        Ok(ids.iter().filter_map(|id| Uuid::parse_str(id).ok()).collect())
+    }
+    
     pub fn cleanup_old_items(&self, keep_days: u32) -> Result<u32, Box<dyn std::error::Error>> {
         let cutoff = (Utc::now() - chrono::Duration::days(keep_days as i64)).timestamp();
         
-        let count = self.conn.execute(
+        let conn = self.conn.lock();
+        let count = conn.execute(
             r#"
             DELETE FROM clipboard_items 
             WHERE favorite = 0 AND pinned = 0 AND timestamp < ?
+              AND id NOT IN (SELECT item_id FROM collection_items)
             "#,
             params![cutoff],
         )?;
         
         // 清理孤立数据
-        self.conn.execute_batch(
+        conn.execute_batch(
             r#"
             DELETE FROM item_tags WHERE item_id NOT IN (SELECT id FROM clipboard_items);
             DELETE FROM item_metadata WHERE item_id NOT IN (SELECT id FROM clipboard_items);
+            DELETE FROM item_representations WHERE item_id NOT IN (SELECT id FROM clipboard_items);
             "#
         )?;
         
@@ -662,8 +2752,10 @@ impl Database {
             cache_size_bytes: 0,
         };
         
+        let conn = self.conn.lock();
+        
         // 获取数据库大小
-        let db_size: i64 = self.conn.query_row(
+        let db_size: i64 = conn.query_row(
             "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
             [],
             |row| row.get(0),
@@ -671,56 +2763,57 @@ impl Database {
         stats.database_size_bytes = db_size as u64;
         
         // 获取各项统计
-        stats.total_items = self.conn.query_row(
+        stats.total_items = conn.query_row(
             "SELECT COUNT(*) FROM clipboard_items",
             [],
             |row| row.get(0),
         )?;
         
-        stats.text_items = self.conn.query_row(
+        stats.text_items = conn.query_row(
             "SELECT COUNT(*) FROM clipboard_items WHERE content_type = 'text'",
             [],
             |row| row.get(0),
         )?;
         
-        stats.image_items = self.conn.query_row(
+        stats.image_items = conn.query_row(
             "SELECT COUNT(*) FROM clipboard_items WHERE content_type = 'image'",
             [],
             |row| row.get(0),
         )?;
         
-        stats.file_items = self.conn.query_row(
+        stats.file_items = conn.query_row(
             "SELECT COUNT(*) FROM clipboard_items WHERE content_type = 'file'",
             [],
             |row| row.get(0),
         )?;
         
-        stats.html_items = self.conn.query_row(
+        stats.html_items = conn.query_row(
             "SELECT COUNT(*) FROM clipboard_items WHERE content_type = 'html'",
             [],
             |row| row.get(0),
         )?;
         
-        stats.favorite_items = self.conn.query_row(
+        stats.favorite_items = conn.query_row(
             "SELECT COUNT(*) FROM clipboard_items WHERE favorite = 1",
             [],
             |row| row.get(0),
         )?;
         
-        stats.pinned_items = self.conn.query_row(
+        stats.pinned_items = conn.query_row(
             "SELECT COUNT(*) FROM clipboard_items WHERE pinned = 1",
             [],
             |row| row.get(0),
         )?;
         
         // 估算总大小（文本长度 + 图片大小）
-        let text_size: i64 = self.conn.query_row(
-            "SELECT SUM(LENGTH(content_json)) FROM clipboard_items",
+        let text_size: i64 = conn.query_row(
+            "SELECT (SELECT IFNULL(SUM(LENGTH(content_json)), 0) FROM clipboard_items)
+                  + (SELECT IFNULL(SUM(LENGTH(content_json)), 0) FROM item_representations)",
             [],
             |row| row.get(0),
         ).unwrap_or(0);
         
-        let image_size: i64 = self.conn.query_row(
+        let image_size: i64 = conn.query_row(
             "SELECT SUM(LENGTH(preview_image)) FROM clipboard_items WHERE preview_image IS NOT NULL",
             [],
             |row| row.get(0),
@@ -732,37 +2825,54 @@ impl Database {
     }
 }
 
-pub struct ClipboardMonitor {
+// 自定义格式超过此大小时不保存，避免 OLE 嵌入数据等撑大数据库
+const MAX_CUSTOM_FORMAT_SIZE: usize = 1024 * 1024;
+/// 检查到期条目的间隔
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ClipboardMonitor<B: ClipboardBackend + ?Sized> {
+    backend: Arc<B>,
     settings: Arc<RwLock<AppSettings>>,
     database: Arc<Database>,
+    capture_state: Arc<Mutex<CaptureState>>,
     event_tx: Sender<ClipboardEvent>,
     running: Arc<std::sync::atomic::AtomicBool>,
 }
 
-impl ClipboardMonitor {
+impl<B: ClipboardBackend + ?Sized + 'static> ClipboardMonitor<B> {
     pub fn new(
+        backend: Arc<B>,
         settings: Arc<RwLock<AppSettings>>,
         database: Arc<Database>,
+        capture_state: Arc<Mutex<CaptureState>>,
         event_tx: Sender<ClipboardEvent>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
+            backend,
             settings,
             database,
+            capture_state,
             event_tx,
             running: Arc::new(std::sync::atomic::AtomicBool::new(false)),
         })
     }
     
     pub fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // 先订阅再启动线程，保证 start 返回后的变更不会丢失
+        let changes = self.backend.subscribe()?;
+        info!("Clipboard monitor using {} backend", self.backend.name());
+        
         self.running.store(true, std::sync::atomic::Ordering::SeqCst);
         
         let running = self.running.clone();
+        let backend = self.backend.clone();
         let settings = self.settings.clone();
         let database = self.database.clone();
+        let capture_state = self.capture_state.clone();
         let event_tx = self.event_tx.clone();
         
         std::thread::spawn(move || {
-            if let Err(e) = Self::monitor_loop(running, settings, database, event_tx) {
+            if let Err(e) = Self::monitor_loop(running, changes, backend, settings, database, capture_state, event_tx) {
                 error!("Clipboard monitor error: {}", e);
             }
         });
@@ -772,260 +2882,930 @@ impl ClipboardMonitor {
     
     pub fn stop(&self) -> Result<(), Box<dyn std::error::Error>> {
         self.running.store(false, std::sync::atomic::Ordering::SeqCst);
+        self.backend.unsubscribe();
         Ok(())
     }
     
     fn monitor_loop(
         running: Arc<std::sync::atomic::AtomicBool>,
+        changes: Receiver<()>,
+        backend: Arc<B>,
         settings: Arc<RwLock<AppSettings>>,
         database: Arc<Database>,
+        capture_state: Arc<Mutex<CaptureState>>,
         event_tx: Sender<ClipboardEvent>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        use windows::Win32::UI::WindowsAndMessaging::*;
-        use windows::Win32::System::DataExchange::*;
+        Self::remove_expired_items(&database, &event_tx);
+        let mut last_expiry_check = Instant::now();
         
-        // 创建隐藏窗口
-        let hwnd = unsafe {
-            let instance = GetModuleHandleW(None)?;
-            let class_name = s!("ClipboardMasterWindow");
-            
-            let wc = WNDCLASSW {
-                lpfnWndProc: Some(Self::window_proc),
-                hInstance: instance,
-                lpszClassName: class_name,
-                ..Default::default()
-            };
+        while running.load(std::sync::atomic::Ordering::SeqCst) {
+            if last_expiry_check.elapsed() >= EXPIRY_CHECK_INTERVAL {
+                Self::remove_expired_items(&database, &event_tx);
+                last_expiry_check = Instant::now();
+            }
             
-            RegisterClassW(&wc)?;
+            // 先检查一次，让定时恢复不必等到下一次剪贴板变更
+            resume_capture_if_due(&capture_state, &event_tx);
             
-            CreateWindowExW(
-                Default::default(),
-                class_name,
-                s!("Clipboard Master"),
-                WS_OVERLAPPEDWINDOW,
-                0, 0, 0, 0,
-                None,
-                None,
-                instance,
-                None,
-            )?
-        };
-        
-        // 注册剪贴板监听
-        unsafe {
-            AddClipboardFormatListener(hwnd)?;
-        }
-        
-        // 消息循环
-        let mut msg = MSG::default();
-        while running.load(std::sync::atomic::Ordering::SeqCst) {
-            unsafe {
-                if PeekMessageW(&mut msg, hwnd, 0, 0, PM_REMOVE).as_bool() {
-                    if msg.message == WM_CLIPBOARDUPDATE {
-                        if let Ok(item) = Self::capture_clipboard_content(&settings) {
-                            if let Err(e) = database.save_item(item.clone()) {
-                                error!("Failed to save clipboard item: {}", e);
-                            } else {
-                                let _ = event_tx.send(ClipboardEvent::ItemAdded(item));
+            match changes.recv_timeout(Duration::from_millis(100)) {
+                Ok(()) => {
+                    // 暂停期间的变更直接丢弃，不读取内容
+                    if resume_capture_if_due(&capture_state, &event_tx) {
+                        info!("Dropped clipboard change while capture is paused");
+                        continue;
+                    }
+                    
+                    // 本程序自身的写入：原条目移到最前，不再重复保存
+                    if let Some(id) = Self::read_self_write_marker(&*backend) {
+                        match database.touch_item(id) {
+                            Ok(true) => {
+                                if let Ok(Some(item)) = database.get_item(id) {
+                                    let _ = event_tx.send(ClipboardEvent::ItemUpdated(item));
+                                }
+                                continue;
+                            }
+                            // 原条目已被删除，按新内容保存
+                            Ok(false) => {}
+                            Err(e) => {
+                                error!("Failed to update clipboard item: {}", e);
+                                continue;
                             }
                         }
                     }
-                    TranslateMessage(&msg);
-                    DispatchMessageW(&msg);
-                } else {
-                    std::thread::sleep(Duration::from_millis(10));
+                    
+                    match Self::capture_clipboard_content(&*backend, &settings) {
+                        Ok(Some(item)) => {
+                            let (policy, interval) = {
+                                let settings = settings.read();
+                                (settings.duplicate_policy, settings.ignore_duplicate_interval)
+                            };
+                            
+                            match database.save_item(item.clone(), policy, interval) {
+                                Ok(SaveResult::Inserted) => {
+                                    let item_id = item.id;
+                                    let _ = event_tx.send(ClipboardEvent::ItemAdded(item));
+                                    Self::notify_saved_searches(&database, &settings, &event_tx, item_id);
+                                }
+                                Ok(SaveResult::Ignored(_)) => {}
+                                Ok(SaveResult::MovedToTop(id)) => {
+                                    if let Ok(Some(item)) = database.get_item(id) {
+                                        let _ = event_tx.send(ClipboardEvent::ItemUpdated(item));
+                                    }
+                                }
+                                Err(e) => error!("Failed to save clipboard item: {}", e),
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => warn!("Failed to read clipboard: {}", e),
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                // 停止时后端结束订阅
+                Err(RecvTimeoutError::Disconnected) if !running.load(std::sync::atomic::Ordering::SeqCst) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err("剪贴板通知通道已关闭".into());
                 }
             }
         }
         
-        // 清理
-        unsafe {
-            RemoveClipboardFormatListener(hwnd)?;
-            DestroyWindow(hwnd)?;
+        Ok(())
+    }
+    
+    // 删除到期的条目并通知界面
+    fn remove_expired_items(database: &Database, event_tx: &Sender<ClipboardEvent>) {
+        match database.delete_expired_items() {
+            Ok(ids) => {
+                for id in ids {
+                    let _ = event_tx.send(ClipboardEvent::ItemRemoved(id));
+                }
+            }
+            Err(e) => warn!("Failed to delete expired items: {}", e),
         }
+    }
+    
+    // 新条目符合已订阅的保存搜索时发送事件
+    fn notify_saved_searches(
+        database: &Database,
+        settings: &Arc<RwLock<AppSettings>>,
+        event_tx: &Sender<ClipboardEvent>,
+        item_id: Uuid,
+    ) {
+        let search_settings = settings.read().search.clone();
+        let searches = match database.get_saved_searches() {
+            Ok(searches) => searches,
+            Err(e) => {
+                warn!("Failed to load saved searches: {}", e);
+                return;
+            }
+        };
         
-        Ok(())
+        for search in searches.into_iter().filter(|search| search.subscribed) {
+            match database.item_matches(item_id, &search.current_query(), &search_settings) {
+                Ok(true) => {
+                    let _ = event_tx.send(ClipboardEvent::SavedSearchMatched {
+                        search_id: search.id,
+                        name: search.name,
+                        item_id,
+                    });
+                }
+                Ok(false) => {}
+                Err(e) => warn!("Failed to match saved search {}: {}", search.name, e),
+            }
+        }
     }
     
-    extern "system" fn window_proc(
-        hwnd: HWND,
-        msg: u32,
-        wparam: WPARAM,
-        lparam: LPARAM,
-    ) -> LRESULT {
-        unsafe {
-            DefWindowProcW(hwnd, msg, wparam, lparam)
+    fn read_self_write_marker(backend: &B) -> Option<Uuid> {
+        let marker = ClipboardFormat::Custom(SELF_WRITE_FORMAT.to_string());
+        if !backend.available_formats().ok()?.contains(&marker) {
+            return None;
+        }
+        
+        match backend.read(&marker) {
+            Ok(Some(ClipboardContent::Custom(_, data))) => {
+                Uuid::parse_str(std::str::from_utf8(&data).ok()?.trim_end_matches('\0')).ok()
+            }
+            _ => None,
         }
     }
     
     fn capture_clipboard_content(
+        backend: &B,
         settings: &Arc<RwLock<AppSettings>>
-    ) -> Result<ClipboardItem, Box<dyn std::error::Error>> {
-        use windows::Win32::UI::WindowsAndMessaging::*;
-        use windows::Win32::System::DataExchange::*;
-        use windows::Win32::Graphics::Gdi::*;
-        
-        unsafe {
-            if !OpenClipboard(None).as_bool() {
-                return Err("无法打开剪贴板".into());
+    ) -> Result<Option<ClipboardItem>, Box<dyn std::error::Error>> {
+        let formats = backend.available_formats()?;
+        let source_app = backend.source_app();
+        let source_window = backend.source_window();
+        
+        let (action, honor_concealed_hints) = {
+            let settings = settings.read();
+            (
+                settings.capture_policy.action_for(source_app.as_deref(), source_window.as_deref()),
+                settings.capture_policy.honor_concealed_hints,
+            )
+        };
+        
+        if action == Some(CaptureAction::Ignore) {
+            info!("Ignored clipboard content from {}", source_app.as_deref().unwrap_or_default());
+            return Ok(None);
+        }
+        
+        let concealed = honor_concealed_hints && capture_policy::is_concealed(&formats, |format| {
+            match backend.read(format) {
+                Ok(Some(ClipboardContent::Custom(_, data))) => Some(data),
+                _ => None,
             }
-            
-            let mut item = ClipboardItem {
-                id: Uuid::new_v4(),
-                content: ClipboardContent::Text("".to_string()),
-                timestamp: Utc::now(),
-                tags: Vec::new(),
-                favorite: false,
-                pinned: false,
-                source_app: Self::get_foreground_app(),
-                source_window: Self::get_foreground_window(),
-                preview_text: String::new(),
-                preview_image: None,
-                metadata: HashMap::new(),
-            };
-            
-            // 检查各种格式
-            if IsClipboardFormatAvailable(CF_UNICODETEXT as u32).as_bool() {
-                item = Self::capture_text(item)?;
-            } else if IsClipboardFormatAvailable(CF_BITMAP as u32).as_bool() {
-                item = Self::capture_image(item, settings)?;
-            } else if IsClipboardFormatAvailable(CF_HDROP as u32).as_bool() {
-                item = Self::capture_files(item)?;
-            } else if IsClipboardFormatAvailable(Self::register_format("HTML Format")?).as_bool() {
-                item = Self::capture_html(item)?;
+        });
+        if concealed {
+            info!("Ignored clipboard content marked as concealed");
+            return Ok(None);
+        }
+        
+        // 读取所有可用格式，单个格式失败不影响其他格式
+        let mut contents = Vec::new();
+        for format in formats {
+            if !Self::should_capture(&format, settings) || !action.is_none_or(|action| action.allows(&format)) {
+                continue;
             }
             
-            CloseClipboard();
-            Ok(item)
+            match backend.read(&format) {
+                Ok(Some(ClipboardContent::Custom(name, data))) if data.len() > MAX_CUSTOM_FORMAT_SIZE => {
+                    warn!("Skipping clipboard format {} ({} bytes)", name, data.len());
+                }
+                Ok(Some(content)) => contents.push(content),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read clipboard format {:?}: {}", format, e),
+            }
+        }
+        
+        // Windows 的 HTML Format 带有描述头，只保留片段，来源地址放入元数据
+        let mut metadata = HashMap::new();
+        for content in contents.iter_mut() {
+            if let ClipboardContent::Html(payload) = content {
+                if let Some(cf_html) = html::parse_cf_html(payload) {
+                    if let Some(url) = cf_html.source_url {
+                        metadata.insert("source_url".to_string(), url);
+                    }
+                    *payload = cf_html.fragment;
+                }
+            }
+        }
+        
+        // 按优先级选出主表示，其余作为其他表示保存
+        contents.sort_by_key(|content| Self::format_priority(&ClipboardFormat::of(content)));
+        let mut contents = contents.into_iter();
+        let content = match contents.next() {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+        
+        let mut item = ClipboardItem {
+            id: Uuid::new_v4(),
+            content,
+            timestamp: Utc::now(),
+            tags: Vec::new(),
+            favorite: false,
+            pinned: false,
+            source_app,
+            source_window,
+            preview_text: String::new(),
+            preview_image: None,
+            metadata,
+            alternatives: contents.collect(),
+            expires_at: None,
+            encrypted_content: None,
+        };
+        
+        Self::build_preview(&mut item, settings);
+        
+        // 分类和规则按原文匹配，须在隐藏敏感信息之前执行；两者只写入类型和用户配置的值，不会带出原文
+        classify::classify_item(&mut item);
+        rules::apply_rules(&settings.read().auto_tag_rules, &mut item);
+        
+        let protection = sensitive::protect_item(&mut item, &settings.read().sensitive);
+        match protection {
+            sensitive::Protection::Clean => {}
+            sensitive::Protection::Skip => {
+                info!("Skipped clipboard content containing sensitive data");
+                return Ok(None);
+            }
+            sensitive::Protection::Protected { kinds, policy, preview } => {
+                info!("Detected sensitive data {:?}, applying {:?} policy", kinds, policy);
+                item.preview_text = Self::truncate_preview(&preview);
+            }
+        }
+        
+        // 与敏感数据的到期时间同时存在时取较早的一个
+        if let Some(CaptureAction::Expire(secs)) = action {
+            let expires_at = item.timestamp + chrono::Duration::seconds(i64::from(secs));
+            item.expires_at = Some(item.expires_at.map_or(expires_at, |current| current.min(expires_at)));
         }
+        
+        Ok(Some(item))
     }
     
-    fn capture_text(mut item: ClipboardItem) -> Result<ClipboardItem, Box<dyn std::error::Error>> {
-        unsafe {
-            let h_mem = GetClipboardData(CF_UNICODETEXT as u32)?;
-            let ptr = GlobalLock(h_mem)? as *const u16;
-            
-            let mut len = 0;
-            while *ptr.add(len) != 0 {
-                len += 1;
+    fn should_capture(format: &ClipboardFormat, settings: &Arc<RwLock<AppSettings>>) -> bool {
+        let settings = settings.read();
+        match format {
+            ClipboardFormat::Text => settings.save_text,
+            ClipboardFormat::Html | ClipboardFormat::RichText => settings.save_html,
+            ClipboardFormat::Image => settings.save_images,
+            ClipboardFormat::Files => settings.save_files,
+            ClipboardFormat::Custom(name) => name != SELF_WRITE_FORMAT && capture_policy::is_capturable_format(name),
+        }
+    }
+    
+    fn format_priority(format: &ClipboardFormat) -> u8 {
+        match format {
+            ClipboardFormat::Files => 0,
+            ClipboardFormat::Text => 1,
+            ClipboardFormat::Image => 2,
+            ClipboardFormat::Html => 3,
+            ClipboardFormat::RichText => 4,
+            ClipboardFormat::Custom(_) => 5,
+        }
+    }
+    
+    fn build_preview(item: &mut ClipboardItem, settings: &Arc<RwLock<AppSettings>>) {
+        // 创建缩略图，无法解码的图片不影响其他表示的保存
+        let compress_images = settings.read().compress_images;
+        for content in std::iter::once(&mut item.content).chain(item.alternatives.iter_mut()) {
+            if let ClipboardContent::Image(img) = content {
+                if compress_images && img.thumbnail.is_empty() {
+                    match Self::create_thumbnail(&img.data) {
+                        Ok(thumbnail) => img.thumbnail = thumbnail,
+                        Err(e) => warn!("Failed to create thumbnail: {}", e),
+                    }
+                }
+                if item.preview_image.is_none() && !img.thumbnail.is_empty() {
+                    item.preview_image = Some(img.thumbnail.clone());
+                }
             }
-            
-            let text = String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len));
-            GlobalUnlock(h_mem);
-            
-            item.content = ClipboardContent::Text(text.clone());
-            item.preview_text = if text.len() > 100 {
-                format!("{}...", &text[..100])
-            } else {
-                text
-            };
-            
-            Ok(item)
         }
+        
+        item.preview_text = match &item.content {
+            ClipboardContent::Text(text) => Self::truncate_preview(text),
+            ClipboardContent::Image(img) => format!("[Image {}x{}]", img.width, img.height),
+            ClipboardContent::FileList(files) => match files.as_slice() {
+                [file] => file.path.to_string_lossy().to_string(),
+                _ => format!("[{} files]", files.len()),
+            },
+            ClipboardContent::Html(html) => Self::truncate_preview(&html::html_to_text(html)),
+            ClipboardContent::RichText(_) => "[Rich Text]".to_string(),
+            ClipboardContent::Custom(name, _) => format!("[{}]", name),
+        };
     }
     
-    fn capture_image(
-        mut item: ClipboardItem,
-        settings: &Arc<RwLock<AppSettings>>
-    ) -> Result<ClipboardItem, Box<dyn std::error::Error>> {
-        use image::{ImageBuffer, Rgba};
+    fn truncate_preview(text: &str) -> String {
+        if text.chars().count() > 100 {
+            format!("{}...", text.chars().take(100).collect::<String>())
+        } else {
+            text.to_string()
+        }
+    }
+    
+    fn create_thumbnail(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let img = image::load_from_memory(data)?;
+        let thumb_img = img.thumbnail(128, 128);
+        
+        let mut thumb_data = Vec::new();
+        thumb_img.write_to(
+            &mut std::io::Cursor::new(&mut thumb_data),
+            image::ImageFormat::Png,
+        )?;
         
-        unsafe {
-            let h_bitmap = GetClipboardData(CF_BITMAP as u32)? as HBITMAP;
-            
-            // 获取位图信息
-            let mut bmp = BITMAP::default();
-            GetObjectW(
-                h_bitmap,
-                std::mem::size_of::<BITMAP>() as i32,
-                &mut bmp as *mut _ as *mut std::ffi::c_void,
-            );
-            
-            // 创建图像缓冲区
-            let width = bmp.bmWidth as u32;
-            let height = bmp.bmHeight as u32;
-            let bits_ptr = bmp.bmBits as *const u8;
-            let bits_len = (width * height * 4) as usize;
-            
-            let slice = std::slice::from_raw_parts(bits_ptr, bits_len);
-            let img_buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, slice)
-                .ok_or("无法创建图像缓冲区")?;
-            
-            // 转换为PNG
-            let mut png_data = Vec::new();
-            img_buffer.write_to(
-                &mut std::io::Cursor::new(&mut png_data),
-                image::ImageFormat::Png,
-            )?;
-            
-            // 创建缩略图
-            let thumbnail = if settings.read().compress_images {
-                let thumb_img = image::imageops::thumbnail(&img_buffer, 128, 128);
-                let mut thumb_data = Vec::new();
-                thumb_img.write_to(
-                    &mut std::io::Cursor::new(&mut thumb_data),
-                    image::ImageFormat::Png,
-                )?;
-                Some(thumb_data)
-            } else {
-                None
-            };
-            
-            item.content = ClipboardContent::Image(ImageData {
-                data: png_data,
-                width,
-                height,
+        Ok(thumb_data)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn test_settings() -> AppSettings {
+        let dir = std::env::temp_dir().join(format!("clipboard-master-test-{}", Uuid::new_v4()));
+        let mut settings = ClipboardCore::default_settings();
+        settings.database_path = dir.join("clipboard.db").to_string_lossy().to_string();
+        settings.cache_path = dir.join("cache").to_string_lossy().to_string();
+        settings
+    }
+    
+    fn next_event(core: &ClipboardCore) -> ClipboardEvent {
+        core.receive_events().recv_timeout(Duration::from_secs(2)).expect("没有收到事件")
+    }
+    
+    #[test]
+    fn monitor_captures_changes_but_not_self_writes() {
+        let backend = Arc::new(InMemoryBackend::new());
+        let mut core = ClipboardCore::with_backend(backend.clone(), test_settings()).unwrap();
+        core.start().unwrap();
+        
+        backend.push(ClipboardContent::Text("hello".to_string()));
+        let item = match next_event(&core) {
+            ClipboardEvent::ItemAdded(item) => item,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        assert!(matches!(&item.content, ClipboardContent::Text(text) if text == "hello"));
+        
+        // 写回的内容带有 SELF_WRITE_FORMAT 标记，只会把原条目移到最前
+        core.copy_to_clipboard(item.id, CopyOptions::default()).unwrap();
+        assert!(backend.contents().iter().any(|content| {
+            matches!(content, ClipboardContent::Custom(name, _) if name == SELF_WRITE_FORMAT)
+        }));
+        match next_event(&core) {
+            ClipboardEvent::ItemUpdated(updated) => assert_eq!(updated.id, item.id),
+            event => panic!("unexpected event: {:?}", event),
+        }
+        
+        let items = core.get_recent_items(10).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id, item.id);
+        
+        // 停止时结束订阅，监听线程不必等到下一次剪贴板变更才退出
+        let changes = backend.subscribe().unwrap();
+        core.stop().unwrap();
+        assert_eq!(changes.recv_timeout(Duration::from_secs(1)), Err(RecvTimeoutError::Disconnected));
+    }
+    
+    #[test]
+    fn monitor_captures_every_representation() {
+        let backend = Arc::new(InMemoryBackend::new());
+        let mut core = ClipboardCore::with_backend(backend.clone(), test_settings()).unwrap();
+        core.start().unwrap();
+        
+        // 图片数据无法解码，只是没有缩略图，不影响整次捕获
+        backend.push_all(vec![
+            ClipboardContent::Html("<b>hello</b>".to_string()),
+            ClipboardContent::Custom("application/x-test".to_string(), vec![7, 8, 9]),
+            ClipboardContent::Image(ImageData {
+                data: vec![1, 2, 3],
+                width: 1,
+                height: 1,
                 format: ImageFormat::Png,
-                thumbnail: thumbnail.unwrap_or_default(),
-            });
-            
-            item.preview_text = format!("[Image {}x{}]", width, height);
-            item.preview_image = thumbnail;
-            
-            Ok(item)
+                thumbnail: Vec::new(),
+            }),
+            ClipboardContent::Text("hello".to_string()),
+        ]);
+        let item = match next_event(&core) {
+            ClipboardEvent::ItemAdded(item) => item,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        
+        let stored = core.get_item(item.id).unwrap().unwrap();
+        assert_eq!(
+            stored.formats(),
+            [
+                ClipboardFormat::Text,
+                ClipboardFormat::Image,
+                ClipboardFormat::Html,
+                ClipboardFormat::Custom("application/x-test".to_string()),
+            ]
+        );
+        assert_eq!(stored.preview_text, "hello");
+        assert!(stored.preview_image.is_none());
+        assert!(matches!(
+            stored.representation(&ClipboardFormat::Image),
+            Some(ClipboardContent::Image(img)) if img.data == [1, 2, 3] && img.thumbnail.is_empty()
+        ));
+        assert!(matches!(
+            stored.representation(&ClipboardFormat::Custom("application/x-test".to_string())),
+            Some(ClipboardContent::Custom(_, data)) if data == &[7, 8, 9]
+        ));
+        
+        core.stop().unwrap();
+    }
+    
+    // 搜索测试使用的条目，按时间从旧到新
+    struct Fixture {
+        database: Database,
+        names: HashMap<Uuid, &'static str>,
+    }
+    
+    fn day(n: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + n * 86_400, 0).unwrap()
+    }
+    
+    fn fixture() -> Fixture {
+        let database = Database::new(":memory:").unwrap();
+        let mut names = HashMap::new();
+        
+        let text = |text: &str| ClipboardContent::Text(text.to_string());
+        let image = ClipboardContent::Image(ImageData {
+            data: vec![1, 2, 3],
+            width: 1,
+            height: 1,
+            format: ImageFormat::Png,
+            thumbnail: Vec::new(),
+        });
+        
+        let entries = [
+            ("a", text("alpha report"), 1, vec!["work"], "Editor", true, false),
+            ("b", text("beta notes"), 2, vec!["work/projectA"], "Browser", false, true),
+            ("c", ClipboardContent::Html("<b>alpha</b> page".to_string()), 3, vec!["personal"], "Browser", false, false),
+            ("d", image, 4, vec![], "Paint", false, false),
+            ("e", text("gamma alpha"), 5, vec!["work"], "Editor", true, true),
+        ];
+        
+        for (name, content, n, tags, source, favorite, pinned) in entries {
+            let item = ClipboardItem {
+                timestamp: day(n),
+                tags: tags.into_iter().map(str::to_string).collect(),
+                favorite,
+                pinned,
+                source_app: Some(source.to_string()),
+                ..ClipboardItem::test_item(content)
+            };
+            names.insert(item.id, name);
+            database.save_item(item, DuplicatePolicy::KeepBoth, 0).unwrap();
+        }
+        
+        Fixture { database, names }
+    }
+    
+    impl Fixture {
+        // 按结果顺序返回条目名称
+        fn search(&self, query: SearchQuery) -> Vec<&'static str> {
+            self.database.search_items(query, &SearchSettings::default()).unwrap()
+                .iter()
+                .map(|item| self.names[&item.id])
+                .collect()
+        }
+        
+        // 按相关度返回条目名称和命中片段
+        fn ranked(&self, text: &str) -> Vec<(&'static str, String)> {
+            let query = SearchQuery { text: Some(text.to_string()), ..Default::default() };
+            self.database.search_ranked(query, &SearchSettings::default()).unwrap()
+                .into_iter()
+                .map(|result| (self.names[&result.item.id], result.snippet))
+                .collect()
+        }
+        
+        // 集合中条目的名称，按位置排序
+        fn collection(&self, id: Uuid) -> Vec<&'static str> {
+            self.database.get_collection_items(id).unwrap()
+                .iter()
+                .map(|item| self.names[&item.id])
+                .collect()
+        }
+        
+        fn item(&self, name: &str) -> ClipboardItem {
+            let id = self.names.iter().find(|(_, n)| **n == name).map(|(id, _)| *id).unwrap();
+            self.database.get_item(id).unwrap().unwrap()
         }
     }
     
-    fn register_format(format_name: &str) -> Result<u32, Box<dyn std::error::Error>> {
-        use windows::Win32::System::DataExchange::*;
+    fn expr(input: &str) -> Option<QueryExpr> {
+        SearchQuery::parse(input).unwrap().expr
+    }
+    
+    #[test]
+    fn search_without_filters_returns_everything_newest_first() {
+        let fixture = fixture();
+        assert_eq!(fixture.search(SearchQuery::default()), ["e", "d", "c", "b", "a"]);
+    }
+    
+    #[test]
+    fn search_filters_individually() {
+        let fixture = fixture();
+        
+        let text = SearchQuery { text: Some("alpha".to_string()), ..Default::default() };
+        assert_eq!(fixture.search(text), ["e", "c", "a"]);
+        
+        // 父标签包含子标签
+        let tags = SearchQuery { tags: vec!["work".to_string()], ..Default::default() };
+        assert_eq!(fixture.search(tags), ["e", "b", "a"]);
+        let tags = SearchQuery { tags: vec!["work/projectA".to_string()], ..Default::default() };
+        assert_eq!(fixture.search(tags), ["b"]);
+        
+        let types = SearchQuery { content_types: vec![ContentType::Html, ContentType::Image], ..Default::default() };
+        assert_eq!(fixture.search(types), ["d", "c"]);
+        
+        let favorite = SearchQuery { favorite_only: true, ..Default::default() };
+        assert_eq!(fixture.search(favorite), ["e", "a"]);
+        
+        let pinned = SearchQuery { pinned_only: true, ..Default::default() };
+        assert_eq!(fixture.search(pinned), ["e", "b"]);
+        
+        let dates = SearchQuery { date_from: Some(day(2)), date_to: Some(day(4)), ..Default::default() };
+        assert_eq!(fixture.search(dates), ["d", "c", "b"]);
+        
+        let source = SearchQuery { expr: expr("from:browser"), ..Default::default() };
+        assert_eq!(fixture.search(source), ["c", "b"]);
+        
+        let paged = SearchQuery { limit: Some(2), offset: Some(1), ..Default::default() };
+        assert_eq!(fixture.search(paged), ["d", "c"]);
+        
+        // 只设置 offset 时不限制条数
+        let offset = SearchQuery { offset: Some(3), ..Default::default() };
+        assert_eq!(fixture.search(offset), ["b", "a"]);
+        
+        let negated = SearchQuery { expr: expr("-tag:work"), ..Default::default() };
+        assert_eq!(fixture.search(negated), ["d", "c"]);
+    }
+    
+    #[test]
+    fn search_filters_combined() {
+        let fixture = fixture();
+        
+        let query = SearchQuery {
+            text: Some("alpha".to_string()),
+            tags: vec!["work".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(fixture.search(query), ["e", "a"]);
+        
+        let query = SearchQuery {
+            text: Some("alpha".to_string()),
+            content_types: vec![ContentType::Text],
+            favorite_only: true,
+            pinned_only: true,
+            ..Default::default()
+        };
+        assert_eq!(fixture.search(query), ["e"]);
+        
+        let query = SearchQuery {
+            tags: vec!["work".to_string()],
+            date_from: Some(day(2)),
+            date_to: Some(day(5)),
+            expr: expr("from:editor"),
+            ..Default::default()
+        };
+        assert_eq!(fixture.search(query), ["e"]);
+        
+        let query = SearchQuery {
+            content_types: vec![ContentType::Text, ContentType::Html],
+            expr: expr("from:browser OR is:favorite"),
+            limit: Some(2),
+            offset: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(fixture.search(query), ["c", "b"]);
         
-        unsafe {
-            let name_wide: Vec<u16> = format_name.encode_utf16().chain(std::iter::once(0)).collect();
-            let format = RegisterClipboardFormatW(PCWSTR(name_wide.as_ptr()))?;
-            Ok(format as u32)
+        let query = SearchQuery {
+            text: Some("alpha".to_string()),
+            date_to: Some(day(3)),
+            pinned_only: true,
+            ..Default::default()
+        };
+        assert!(fixture.search(query).is_empty());
+    }
+    
+    #[test]
+    fn full_text_search_ranks_results_and_tracks_changes() {
+        let fixture = fixture();
+        
+        // 命中次数多的条目排在前面，片段中标记命中的文本
+        let mut item = fixture.item("b");
+        item.content = ClipboardContent::Text("alpha alpha alpha".to_string());
+        fixture.database.update_item(item).unwrap();
+        let results = fixture.ranked("alpha");
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].0, "b");
+        assert!(results[0].1.contains("<mark>alpha</mark>"), "{}", results[0].1);
+        
+        // 更新内容后旧文本不再命中
+        let mut item = fixture.item("a");
+        item.content = ClipboardContent::Text("delta summary".to_string());
+        fixture.database.update_item(item).unwrap();
+        assert!(!fixture.ranked("alpha").iter().any(|(name, _)| *name == "a"));
+        assert_eq!(fixture.ranked("delta").iter().map(|(name, _)| *name).collect::<Vec<_>>(), ["a"]);
+        
+        // 删除的条目从索引中移除，VACUUM 之后索引仍指向正确的条目
+        let mut item = fixture.item("e");
+        item.expires_at = Some(day(0));
+        fixture.database.update_item(item).unwrap();
+        assert_eq!(fixture.database.delete_expired_items().unwrap().len(), 1);
+        fixture.database.conn.lock().execute_batch("VACUUM").unwrap();
+        let names: Vec<_> = fixture.ranked("alpha").into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"b") && names.contains(&"c"));
+        assert_eq!(fixture.ranked("gamma"), []);
+        
+        // 与索引连接时其他条件中的 id 不能有歧义
+        let query = SearchQuery {
+            text: Some("alpha".to_string()),
+            expr: expr("-tag:personal"),
+            ..Default::default()
+        };
+        let results = fixture.database.search_ranked(query, &SearchSettings::default()).unwrap();
+        assert_eq!(results.iter().map(|result| fixture.names[&result.item.id]).collect::<Vec<_>>(), ["b"]);
+    }
+    
+    #[test]
+    fn regex_search_matches_text_beyond_the_preview() {
+        let fixture = fixture();
+        let mut item = fixture.item("b");
+        item.content = ClipboardContent::Text(format!("{}\nError: code 4711 at line 9", "x".repeat(200)));
+        fixture.database.update_item(item).unwrap();
+        
+        let query = SearchQuery { expr: expr(r#"regex:"code \d{4}""#), ..Default::default() };
+        assert_eq!(fixture.search(query), ["b"]);
+        
+        let query = SearchQuery { expr: expr("regex:^gamma"), ..Default::default() };
+        assert_eq!(fixture.search(query), ["e"]);
+    }
+    
+    #[test]
+    fn cleanup_keeps_items_in_collections() {
+        let fixture = fixture();
+        let board = Collection::new("Board");
+        fixture.database.insert_collection(&board).unwrap();
+        fixture.database.add_to_collection(board.id, fixture.item("c").id, None).unwrap();
+        
+        // 收藏、置顶和集合中的条目都保留，只删除 d
+        assert_eq!(fixture.database.cleanup_old_items(30).unwrap(), 1);
+        assert_eq!(fixture.search(SearchQuery::default()), ["e", "c", "b", "a"]);
+        assert_eq!(fixture.collection(board.id), ["c"]);
+    }
+    
+    #[test]
+    fn collections_keep_their_order_across_moves() {
+        let fixture = fixture();
+        let database = &fixture.database;
+        let id = |name: &str| fixture.item(name).id;
+        let board = Collection::new("Board");
+        let other = Collection::new("Other");
+        database.insert_collection(&board).unwrap();
+        database.insert_collection(&other).unwrap();
+        assert!(database.insert_collection(&Collection::new("board")).is_err());
+        
+        for name in ["a", "b", "c"] {
+            assert!(database.add_to_collection(board.id, id(name), None).unwrap());
+        }
+        assert!(database.add_to_collection(board.id, id("d"), Some(0)).unwrap());
+        assert!(!database.add_to_collection(board.id, id("a"), None).unwrap());
+        assert_eq!(fixture.collection(board.id), ["d", "a", "b", "c"]);
+        
+        database.move_in_collection(board.id, id("c"), 1).unwrap();
+        assert_eq!(fixture.collection(board.id), ["d", "c", "a", "b"]);
+        database.move_in_collection(board.id, id("d"), 99).unwrap();
+        assert_eq!(fixture.collection(board.id), ["c", "a", "b", "d"]);
+        
+        // 新的顺序必须恰好包含全部条目
+        assert!(database.reorder_collection(board.id, &[id("a"), id("b"), id("c")]).is_err());
+        assert!(database.reorder_collection(board.id, &[id("a"), id("a"), id("b"), id("c")]).is_err());
+        database.reorder_collection(board.id, &[id("a"), id("b"), id("c"), id("d")]).unwrap();
+        assert_eq!(fixture.collection(board.id), ["a", "b", "c", "d"]);
+        
+        database.move_to_collection(id("a"), board.id, other.id, None).unwrap();
+        assert!(database.move_to_collection(id("a"), board.id, other.id, None).is_err());
+        assert_eq!(fixture.collection(board.id), ["b", "c", "d"]);
+        assert_eq!(fixture.collection(other.id), ["a"]);
+        
+        // 复制只添加关联，条目本身不会重复
+        assert!(database.add_to_collection(other.id, id("c"), Some(0)).unwrap());
+        assert_eq!(fixture.collection(other.id), ["c", "a"]);
+        assert_eq!(
+            database.get_item_collections(id("c")).unwrap().iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            ["Board", "Other"]
+        );
+        assert_eq!(database.search_items(SearchQuery::default(), &SearchSettings::default()).unwrap().len(), 5);
+        
+        assert!(database.remove_from_collection(board.id, id("c")).unwrap());
+        assert!(!database.remove_from_collection(board.id, id("c")).unwrap());
+        database.move_in_collection(board.id, id("d"), 0).unwrap();
+        assert_eq!(fixture.collection(board.id), ["d", "b"]);
+        
+        // 删除集合不删除条目
+        assert!(database.delete_collection(other.id).unwrap());
+        assert!(database.get_item(id("a")).unwrap().is_some());
+        assert_eq!(database.get_item_collections(id("c")).unwrap().len(), 0);
+    }
+    
+    #[test]
+    fn frecency_puts_recently_used_items_first() {
+        let fixture = fixture();
+        let settings = SearchSettings::default();
+        for name in ["a", "a", "c"] {
+            fixture.database.record_access(fixture.item(name).id).unwrap();
         }
+        
+        // 使用过的按次数排序，其余按时间衰减
+        let items = fixture.database.get_recent_items_sorted(10, SortMode::Frecency, &settings).unwrap();
+        let names: Vec<_> = items.iter().map(|item| fixture.names[&item.id]).collect();
+        assert_eq!(names, ["a", "c", "e", "d", "b"]);
+        
+        let query = SearchQuery { text: Some("alpha".to_string()), sort: SortMode::Frecency, ..Default::default() };
+        let results = fixture.database.search_ranked(query, &settings).unwrap();
+        let names: Vec<_> = results.iter().map(|result| fixture.names[&result.item.id]).collect();
+        assert_eq!(names, ["a", "c", "e"]);
     }
     
-    fn get_foreground_app() -> Option<String> {
-        use windows::Win32::UI::WindowsAndMessaging::*;
+    #[test]
+    fn search_history_merges_typing_and_prunes_old_queries() {
+        let database = Database::new(":memory:").unwrap();
+        let queries = |entries: Vec<SearchHistoryEntry>| -> Vec<String> {
+            entries.into_iter().map(|entry| entry.query).collect()
+        };
         
-        unsafe {
-            let hwnd = GetForegroundWindow();
-            let mut text: [u16; 256] = [0; 256];
-            let len = GetWindowTextW(hwnd, &mut text);
-            
-            if len > 0 {
-                Some(String::from_utf16_lossy(&text[..len as usize]))
-            } else {
-                None
+        database.record_search("rust", 4, 3).unwrap();
+        database.record_search("rust", 5, 3).unwrap();
+        // 连续输入的前缀只保留最后一个
+        database.record_search("reg", 1, 3).unwrap();
+        database.record_search("regex", 2, 3).unwrap();
+        
+        assert_eq!(queries(database.get_recent_searches(10).unwrap()), ["regex", "rust"]);
+        let frequent = database.get_frequent_searches(10).unwrap();
+        assert_eq!((frequent[0].query.as_str(), frequent[0].use_count, frequent[0].result_count), ("rust", 2, 5));
+        
+        // 前缀补全不区分大小写，常用的在前，不包含与前缀相同的查询
+        assert_eq!(queries(database.complete_search("R", 10).unwrap()), ["rust", "regex"]);
+        assert_eq!(queries(database.complete_search("rust", 10).unwrap()), Vec::<String>::new());
+        assert_eq!(queries(database.complete_search("re", 10).unwrap()), ["regex"]);
+        assert_eq!(queries(database.complete_search("%", 10).unwrap()), Vec::<String>::new());
+        
+        // 超过 MaxSearchHistory 时删除最早的查询
+        database.record_search("alpha", 0, 3).unwrap();
+        database.record_search("beta", 0, 3).unwrap();
+        assert_eq!(queries(database.get_recent_searches(10).unwrap()), ["beta", "alpha", "regex"]);
+        
+        database.clear_search_history().unwrap();
+        assert!(database.get_recent_searches(10).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn core_records_searches_unless_disabled() {
+        let core = ClipboardCore::with_backend(Arc::new(InMemoryBackend::new()), test_settings()).unwrap();
+        
+        core.search_items(SearchQuery::parse("tag:work hello").unwrap()).unwrap();
+        // 翻页不重复记录
+        core.search_items(SearchQuery { offset: Some(20), ..SearchQuery::parse("next page").unwrap() }).unwrap();
+        let recent = core.get_recent_searches(10).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].query, "tag:work hello");
+        
+        // 直接改内存中的设置，避免写入用户的配置文件
+        core.settings.write().search.save_search_history = false;
+        core.search_items(SearchQuery::parse("private").unwrap()).unwrap();
+        assert_eq!(core.get_recent_searches(10).unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn saved_searches_track_new_items() {
+        let backend = Arc::new(InMemoryBackend::new());
+        let settings = test_settings();
+        let export_path = Path::new(&settings.cache_path).with_file_name("saved-searches.json");
+        let mut core = ClipboardCore::with_backend(backend.clone(), settings).unwrap();
+        
+        let reports = core.create_saved_search(" Reports ", SearchQuery::parse("report -tag:done").unwrap()).unwrap();
+        assert_eq!(reports.name, "Reports");
+        assert!(core.create_saved_search("reports", SearchQuery::default()).is_err());
+        let mut muted = core.create_saved_search("Muted", SearchQuery::parse("report").unwrap()).unwrap();
+        muted.subscribed = false;
+        muted.name = " Muted reports ".to_string();
+        core.update_saved_search(muted).unwrap();
+        core.create_saved_search("Other", SearchQuery::parse("invoice").unwrap()).unwrap();
+        
+        core.start().unwrap();
+        backend.push(ClipboardContent::Text("weekly report".to_string()));
+        let item = match next_event(&core) {
+            ClipboardEvent::ItemAdded(item) => item,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        // 只有订阅且符合查询的保存搜索发送事件
+        match next_event(&core) {
+            ClipboardEvent::SavedSearchMatched { search_id, name, item_id } => {
+                assert_eq!((search_id, name.as_str(), item_id), (reports.id, "Reports", item.id));
             }
+            event => panic!("unexpected event: {:?}", event),
         }
+        assert!(core.receive_events().recv_timeout(Duration::from_millis(300)).is_err());
+        core.stop().unwrap();
+        
+        assert_eq!(core.count_saved_search(reports.id).unwrap(), 1);
+        assert_eq!(core.get_saved_search_items(reports.id).unwrap()[0].item.id, item.id);
+        core.add_tags(item.id, vec!["done".to_string()]).unwrap();
+        assert_eq!(core.count_saved_search(reports.id).unwrap(), 0);
+        
+        // 导入时跳过 id 或名称已存在的搜索
+        core.export_saved_searches(&export_path).unwrap();
+        assert!(core.delete_saved_search(reports.id).unwrap());
+        assert_eq!(core.import_saved_searches(&export_path).unwrap(), 1);
+        let names: Vec<_> = core.get_saved_searches().unwrap().into_iter().map(|search| search.name).collect();
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&"Reports".to_string()));
+        assert!(names.contains(&"Muted reports".to_string()));
     }
     
-    fn get_foreground_window() -> Option<String> {
-        use windows::Win32::UI::WindowsAndMessaging::*;
-        use windows::Win32::Foundation::HWND;
+    #[test]
+    fn saved_searches_resolve_relative_dates_when_run() {
+        let mut search = SavedSearch::new("This week", SearchQuery::parse("report after:-7d").unwrap());
+        search.query.sort = SortMode::Frecency;
+        // 模拟一个月前保存的查询，保存时解析出的日期已经过期
+        let stale = Utc::now() - chrono::Duration::days(37);
+        search.query.expr = Some(QueryExpr::After(stale));
+        
+        let query = search.current_query();
+        assert_eq!(query.text.as_deref(), Some("report"));
+        assert_eq!(query.sort, SortMode::Frecency);
+        match query.expr {
+            Some(QueryExpr::After(time)) => assert!(time > Utc::now() - chrono::Duration::days(8)),
+            expr => panic!("unexpected expr: {:?}", expr),
+        }
         
-        unsafe {
-            let hwnd = GetForegroundWindow();
-            let mut class_name: [u16; 256] = [0; 256];
-            let len = GetClassNameW(hwnd, &mut class_name);
-            
-            if len > 0 {
-                Some(String::from_utf16_lossy(&class_name[..len as usize]))
-            } else {
-                None
-            }
+        // 没有原始输入时按保存的条件执行
+        search.query.input = None;
+        assert_eq!(search.current_query().expr, Some(QueryExpr::After(stale)));
+    }
+    
+    #[test]
+    fn paused_capture_drops_changes_until_resumed() {
+        let backend = Arc::new(InMemoryBackend::new());
+        let mut core = ClipboardCore::with_backend(backend.clone(), test_settings()).unwrap();
+        core.start().unwrap();
+        
+        // 手动暂停：变更被丢弃，直到 resume_capture
+        let state = core.pause_capture(None).unwrap();
+        assert_eq!(state, CaptureState { paused: true, resume_at: None });
+        assert!(matches!(next_event(&core), ClipboardEvent::CaptureStateChanged(s) if s == state));
+        backend.push(ClipboardContent::Text("while paused".to_string()));
+        assert!(core.receive_events().recv_timeout(Duration::from_millis(300)).is_err());
+        
+        core.resume_capture();
+        assert!(matches!(next_event(&core), ClipboardEvent::CaptureStateChanged(s) if !s.paused));
+        // 未暂停时恢复不发送事件
+        core.resume_capture();
+        backend.push(ClipboardContent::Text("after resume".to_string()));
+        match next_event(&core) {
+            ClipboardEvent::ItemAdded(item) => assert_eq!(item.preview_text, "after resume"),
+            event => panic!("unexpected event: {:?}", event),
+        }
+        
+        // 定时暂停：到时间后监控线程自动恢复，无需新的剪贴板变更
+        let state = core.pause_capture(Some(Duration::from_millis(300))).unwrap();
+        assert!(state.paused && state.resume_at.is_some());
+        assert!(matches!(next_event(&core), ClipboardEvent::CaptureStateChanged(s) if s == state));
+        backend.push(ClipboardContent::Text("dropped".to_string()));
+        assert!(matches!(next_event(&core), ClipboardEvent::CaptureStateChanged(s) if s == CaptureState::default()));
+        assert_eq!(core.capture_state(), CaptureState::default());
+        
+        backend.push(ClipboardContent::Text("captured".to_string()));
+        match next_event(&core) {
+            ClipboardEvent::ItemAdded(item) => assert_eq!(item.preview_text, "captured"),
+            event => panic!("unexpected event: {:?}", event),
         }
+        // 暂停期间的变更没有保存
+        let mut previews: Vec<_> = core.get_recent_items(10).unwrap().into_iter().map(|item| item.preview_text).collect();
+        previews.sort();
+        assert_eq!(previews, ["after resume", "captured"]);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn rules_match_content_before_it_is_masked() {
+        let backend = Arc::new(InMemoryBackend::new());
+        let mut core = ClipboardCore::with_backend(backend.clone(), test_settings()).unwrap();
+        core.settings.write().auto_tag_rules = vec![TagRule {
+            name: "wifi".to_string(),
+            enabled: true,
+            conditions: RuleConditions { pattern: Some("hunter2".to_string()), ..Default::default() },
+            actions: RuleActions { tags: vec!["wifi".to_string()], ..Default::default() },
+        }];
+        core.start().unwrap();
+        
+        backend.push(ClipboardContent::Text("password: hunter2".to_string()));
+        let item = match next_event(&core) {
+            ClipboardEvent::ItemAdded(item) => item,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        assert!(matches!(&item.content, ClipboardContent::Text(text) if text == "password: ********"));
+        assert_eq!(item.tags, ["wifi"]);
+    }
+}
@@ -0,0 +1,489 @@
+// 数据库结构迁移
+//
+// 版本号保存在 `PRAGMA user_version` 中。每个迁移在独立的事务中执行，
+// 并在同一事务内更新版本号，失败时数据库保持在上一个版本。
+// 新增表或列时在 `MIGRATIONS` 末尾追加迁移，不要修改已发布的迁移。
+
+use std::path::{Path, PathBuf};
+
+use log::info;
+use rusqlite::{params, Connection, Transaction};
+
+use crate::{ClipboardContent, Database};
+
+struct Migration {
+    version: u32,
+    description: &'static str,
+    up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "initial schema", up: initial_schema },
+    Migration { version: 2, description: "content hash", up: content_hash },
+    Migration { version: 3, description: "full-text index", up: full_text_index },
+    Migration { version: 4, description: "last accessed time", up: last_accessed },
+    Migration { version: 5, description: "search history use count", up: search_history_use_count },
+    Migration { version: 6, description: "saved searches", up: saved_searches },
+    Migration { version: 7, description: "collections", up: collections },
+    Migration { version: 8, description: "sensitive items", up: sensitive_items },
+];
+
+/// 当前程序支持的数据库版本
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// 将数据库升级到 `SCHEMA_VERSION`，升级前在同目录下备份
+pub fn migrate(conn: &mut Connection, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let current: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    
+    if current > SCHEMA_VERSION {
+        return Err(format!(
+            "数据库版本 {} 高于当前程序支持的版本 {}，请升级程序",
+            current, SCHEMA_VERSION
+        ).into());
+    }
+    
+    if current == SCHEMA_VERSION {
+        return Ok(());
+    }
+    
+    // 新建的空数据库无需备份
+    let has_tables: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_tables {
+        let backup = backup_path(path, current);
+        conn.execute("VACUUM INTO ?", [backup.to_string_lossy()])?;
+        info!("Backed up database to {}", backup.display());
+    }
+    
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        info!("Migrating database to version {} ({})", migration.version, migration.description);
+        
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)
+            .map_err(|e| format!("数据库迁移到版本 {} 失败: {}", migration.version, e))?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+    
+    Ok(())
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let timestamp = chrono::Local::now().format("%Y%m%d%H%M%S");
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.{}.bak", version, timestamp));
+    path.with_file_name(name)
+}
+
+fn column_exists(tx: &Transaction, table: &str, column: &str) -> rusqlite::Result<bool> {
+    tx.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = ?",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+// 版本 1：引入版本号之前的结构，旧数据库中已存在的表保持不变
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        -- 主表
+        CREATE TABLE IF NOT EXISTS clipboard_items (
+            id TEXT PRIMARY KEY,
+            content_type TEXT NOT NULL,
+            content_data BLOB,
+            content_json TEXT,
+            timestamp INTEGER NOT NULL,
+            tags_json TEXT DEFAULT '[]',
+            favorite INTEGER DEFAULT 0,
+            pinned INTEGER DEFAULT 0,
+            source_app TEXT,
+            source_window TEXT,
+            preview_text TEXT,
+            preview_image BLOB,
+            metadata_json TEXT DEFAULT '{}',
+            created_at INTEGER DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER DEFAULT (strftime('%s', 'now')),
+            access_count INTEGER DEFAULT 0
+        );
+        
+        -- 标签表（用于快速搜索）
+        CREATE TABLE IF NOT EXISTS item_tags (
+            item_id TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            created_at INTEGER DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (item_id, tag),
+            FOREIGN KEY (item_id) REFERENCES clipboard_items(id) ON DELETE CASCADE
+        );
+        
+        -- 元数据表
+        CREATE TABLE IF NOT EXISTS item_metadata (
+            item_id TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY (item_id, key),
+            FOREIGN KEY (item_id) REFERENCES clipboard_items(id) ON DELETE CASCADE
+        );
+        
+        -- 其他表示（同一次复制中的 HTML、RTF、图片等）
+        CREATE TABLE IF NOT EXISTS item_representations (
+            item_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            format TEXT NOT NULL,
+            content_json TEXT NOT NULL,
+            PRIMARY KEY (item_id, position),
+            FOREIGN KEY (item_id) REFERENCES clipboard_items(id) ON DELETE CASCADE
+        );
+        
+        -- 搜索历史
+        CREATE TABLE IF NOT EXISTS search_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            query TEXT NOT NULL,
+            timestamp INTEGER DEFAULT (strftime('%s', 'now')),
+            result_count INTEGER DEFAULT 0
+        );
+        
+        CREATE INDEX IF NOT EXISTS idx_items_timestamp ON clipboard_items(timestamp DESC);
+        CREATE INDEX IF NOT EXISTS idx_items_favorite ON clipboard_items(favorite) WHERE favorite = 1;
+        CREATE INDEX IF NOT EXISTS idx_items_pinned ON clipboard_items(pinned) WHERE pinned = 1;
+        CREATE INDEX IF NOT EXISTS idx_items_content_type ON clipboard_items(content_type);
+        CREATE INDEX IF NOT EXISTS idx_items_preview ON clipboard_items(preview_text);
+        CREATE INDEX IF NOT EXISTS idx_items_source ON clipboard_items(source_app);
+        
+        CREATE INDEX IF NOT EXISTS idx_tags_tag ON item_tags(tag);
+        CREATE INDEX IF NOT EXISTS idx_tags_item ON item_tags(item_id);
+        
+        CREATE INDEX IF NOT EXISTS idx_metadata ON item_metadata(key, value);
+        CREATE INDEX IF NOT EXISTS idx_search_history ON search_history(timestamp DESC);
+        "#
+    )
+}
+
+// 版本 2：内容哈希，用于去重
+fn content_hash(tx: &Transaction) -> rusqlite::Result<()> {
+    if !column_exists(tx, "clipboard_items", "content_hash")? {
+        tx.execute("ALTER TABLE clipboard_items ADD COLUMN content_hash TEXT", [])?;
+    }
+    
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_items_hash ON clipboard_items(content_hash)",
+        [],
+    )?;
+    
+    // 为已有条目计算哈希，否则旧条目不会参与去重
+    let rows: Vec<(String, Option<String>)> = tx
+        .prepare("SELECT id, content_json FROM clipboard_items WHERE content_hash IS NULL")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    
+    for (id, content_json) in rows {
+        let content: ClipboardContent = match content_json.and_then(|json| serde_json::from_str(&json).ok()) {
+            Some(content) => content,
+            None => continue,
+        };
+        
+        tx.execute(
+            "UPDATE clipboard_items SET content_hash = ? WHERE id = ?",
+            params![Database::calculate_content_hash(&content, None), id],
+        )?;
+    }
+    
+    Ok(())
+}
+
+// 版本 3：全文索引，由触发器与主表保持同步
+//
+// 索引行通过 `fts_rowid` 与条目关联。主键是 TEXT 类型的 id，隐式 rowid 可能被
+// VACUUM 重新编号；而 FTS 表中只有 rowid 能按索引查找，按其他列删除和更新都要扫描整个索引。
+fn full_text_index(tx: &Transaction) -> rusqlite::Result<()> {
+    if !column_exists(tx, "clipboard_items", "search_text")? {
+        tx.execute("ALTER TABLE clipboard_items ADD COLUMN search_text TEXT", [])?;
+    }
+    if !column_exists(tx, "clipboard_items", "fts_rowid")? {
+        tx.execute("ALTER TABLE clipboard_items ADD COLUMN fts_rowid INTEGER", [])?;
+    }
+    
+    // 为已有条目生成索引文本
+    let rows: Vec<(String, Option<String>)> = tx
+        .prepare("SELECT id, content_json FROM clipboard_items")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    
+    let mut alternatives = tx.prepare(
+        "SELECT content_json FROM item_representations WHERE item_id = ? ORDER BY position"
+    )?;
+    for (id, content_json) in rows {
+        let content_json = match content_json {
+            Some(json) => json,
+            None => continue,
+        };
+        let mut contents: Vec<ClipboardContent> = serde_json::from_str(&content_json).into_iter().collect();
+        for json in alternatives.query_map(params![id], |row| row.get::<_, String>(0))? {
+            contents.extend(serde_json::from_str(&json?).ok());
+        }
+        
+        tx.execute(
+            "UPDATE clipboard_items SET search_text = ? WHERE id = ?",
+            params![Database::search_text(&contents), id],
+        )?;
+    }
+    
+    tx.execute_batch(
+        r#"
+        UPDATE clipboard_items SET fts_rowid = rowid;
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_items_fts_rowid ON clipboard_items(fts_rowid);
+        
+        CREATE VIRTUAL TABLE IF NOT EXISTS items_fts USING fts5(
+            body, tags, source, metadata,
+            tokenize = 'trigram'
+        );
+        
+        INSERT INTO items_fts (rowid, body, tags, source, metadata)
+        SELECT
+            fts_rowid,
+            coalesce(search_text, ''),
+            (SELECT group_concat(value, ' ') FROM json_each(tags_json)),
+            trim(coalesce(source_app, '') || ' ' || coalesce(source_window, '')),
+            (SELECT group_concat(value, ' ') FROM json_each(metadata_json))
+        FROM clipboard_items;
+        
+        -- 新条目取索引中最大的 rowid 加一，已删除条目的编号可以复用
+        CREATE TRIGGER IF NOT EXISTS items_fts_insert AFTER INSERT ON clipboard_items BEGIN
+            UPDATE clipboard_items SET fts_rowid = (SELECT coalesce(max(rowid), 0) + 1 FROM items_fts)
+            WHERE id = new.id;
+            INSERT INTO items_fts (rowid, body, tags, source, metadata)
+            SELECT
+                fts_rowid,
+                coalesce(new.search_text, ''),
+                (SELECT group_concat(value, ' ') FROM json_each(new.tags_json)),
+                trim(coalesce(new.source_app, '') || ' ' || coalesce(new.source_window, '')),
+                (SELECT group_concat(value, ' ') FROM json_each(new.metadata_json))
+            FROM clipboard_items WHERE id = new.id;
+        END;
+        
+        CREATE TRIGGER IF NOT EXISTS items_fts_delete AFTER DELETE ON clipboard_items BEGIN
+            DELETE FROM items_fts WHERE rowid = old.fts_rowid;
+        END;
+        
+        CREATE TRIGGER IF NOT EXISTS items_fts_update
+        AFTER UPDATE OF search_text, tags_json, source_app, source_window, metadata_json ON clipboard_items
+        BEGIN
+            UPDATE items_fts SET
+                body = coalesce(new.search_text, ''),
+                tags = (SELECT group_concat(value, ' ') FROM json_each(new.tags_json)),
+                source = trim(coalesce(new.source_app, '') || ' ' || coalesce(new.source_window, '')),
+                metadata = (SELECT group_concat(value, ' ') FROM json_each(new.metadata_json))
+            WHERE rowid = new.fts_rowid;
+        END;
+        "#
+    )
+}
+
+// 版本 4：最后使用时间，用于综合排序
+fn last_accessed(tx: &Transaction) -> rusqlite::Result<()> {
+    if !column_exists(tx, "clipboard_items", "last_accessed")? {
+        tx.execute("ALTER TABLE clipboard_items ADD COLUMN last_accessed INTEGER", [])?;
+    }
+    
+    Ok(())
+}
+
+// 版本 5：每个查询只保留一行，记录使用次数
+fn search_history_use_count(tx: &Transaction) -> rusqlite::Result<()> {
+    if !column_exists(tx, "search_history", "use_count")? {
+        tx.execute("ALTER TABLE search_history ADD COLUMN use_count INTEGER NOT NULL DEFAULT 1", [])?;
+    }
+    
+    tx.execute_batch(
+        r#"
+        -- 合并重复的查询，保留最新的一行
+        UPDATE search_history SET use_count = (
+            SELECT COUNT(*) FROM search_history AS other WHERE other.query = search_history.query
+        );
+        DELETE FROM search_history WHERE id NOT IN (
+            SELECT MAX(id) FROM search_history GROUP BY query
+        );
+        
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_search_history_query ON search_history(query);
+        "#
+    )
+}
+
+// 版本 6：保存的搜索
+fn saved_searches(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS saved_searches (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            query_json TEXT NOT NULL,
+            subscribed INTEGER DEFAULT 1,
+            created_at INTEGER DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER DEFAULT (strftime('%s', 'now'))
+        );
+        "#
+    )
+}
+
+// 版本 7：手动整理的集合
+fn collections(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS collections (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at INTEGER DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER DEFAULT (strftime('%s', 'now'))
+        );
+        
+        -- 条目与集合的多对多关系，position 为条目在集合中的顺序
+        CREATE TABLE IF NOT EXISTS collection_items (
+            collection_id TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            added_at INTEGER DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (collection_id, item_id),
+            FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+            FOREIGN KEY (item_id) REFERENCES clipboard_items(id) ON DELETE CASCADE
+        );
+        
+        CREATE INDEX IF NOT EXISTS idx_collection_items_position ON collection_items(collection_id, position);
+        CREATE INDEX IF NOT EXISTS idx_collection_items_item ON collection_items(item_id);
+        "#
+    )
+}
+
+// 版本 8：到期自动删除的时间和加密保存的原始内容
+fn sensitive_items(tx: &Transaction) -> rusqlite::Result<()> {
+    if !column_exists(tx, "clipboard_items", "expires_at")? {
+        tx.execute("ALTER TABLE clipboard_items ADD COLUMN expires_at INTEGER", [])?;
+    }
+    if !column_exists(tx, "clipboard_items", "encrypted_content")? {
+        tx.execute("ALTER TABLE clipboard_items ADD COLUMN encrypted_content BLOB", [])?;
+    }
+    
+    tx.execute(
+        "CREATE INDEX IF NOT EXISTS idx_items_expires_at ON clipboard_items(expires_at) WHERE expires_at IS NOT NULL",
+        [],
+    )?;
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // 在临时目录中创建版本 1 的数据库
+    fn version_one_database() -> (Connection, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("clipboard-master-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("clipboard.db");
+        
+        let mut conn = Connection::open(&path).unwrap();
+        let tx = conn.transaction().unwrap();
+        initial_schema(&tx).unwrap();
+        tx.pragma_update(None, "user_version", 1).unwrap();
+        tx.commit().unwrap();
+        
+        (conn, path)
+    }
+    
+    fn user_version(conn: &Connection) -> u32 {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+    }
+    
+    #[test]
+    fn content_hash_is_backfilled_for_existing_rows() {
+        // 版本 1 的数据库中已有一个条目
+        let (mut conn, path) = version_one_database();
+        
+        let content = ClipboardContent::Text("old item".to_string());
+        conn.execute(
+            "INSERT INTO clipboard_items (id, content_type, content_json, timestamp) VALUES ('a', 'text', ?, 0)",
+            params![serde_json::to_string(&content).unwrap()],
+        ).unwrap();
+        
+        migrate(&mut conn, &path).unwrap();
+        
+        let hash: Option<String> = conn
+            .query_row("SELECT content_hash FROM clipboard_items WHERE id = 'a'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(hash, Some(Database::calculate_content_hash(&content, None)));
+    }
+    
+    #[test]
+    fn rows_without_content_do_not_block_migration() {
+        let (mut conn, path) = version_one_database();
+        conn.execute(
+            "INSERT INTO clipboard_items (id, content_type, content_json, timestamp) VALUES ('a', 'text', NULL, 0)",
+            [],
+        ).unwrap();
+        
+        migrate(&mut conn, &path).unwrap();
+        
+        assert_eq!(user_version(&conn), SCHEMA_VERSION);
+        let count: u32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM items_fts JOIN clipboard_items ON fts_rowid = items_fts.rowid WHERE id = 'a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+    
+    #[test]
+    fn index_rows_follow_items_by_fts_rowid() {
+        let (mut conn, path) = version_one_database();
+        for (id, text) in [("a", "alpha"), ("b", "bravo")] {
+            conn.execute(
+                "INSERT INTO clipboard_items (id, content_type, content_json, timestamp) VALUES (?, 'text', ?, 0)",
+                params![id, serde_json::to_string(&ClipboardContent::Text(text.to_string())).unwrap()],
+            ).unwrap();
+        }
+        migrate(&mut conn, &path).unwrap();
+        
+        // 删除最后一个条目后新条目复用其编号，不会与现有的索引行冲突
+        conn.execute("DELETE FROM clipboard_items WHERE id = 'b'", []).unwrap();
+        conn.execute(
+            "INSERT INTO clipboard_items (id, content_type, content_json, timestamp, search_text) VALUES ('c', 'text', '', 0, 'charlie')",
+            [],
+        ).unwrap();
+        conn.execute("UPDATE clipboard_items SET search_text = 'delta' WHERE id = 'a'", []).unwrap();
+        
+        let indexed = |conn: &Connection| -> Vec<(String, String)> {
+            conn.prepare(
+                "SELECT id, body FROM items_fts JOIN clipboard_items ON fts_rowid = items_fts.rowid ORDER BY id"
+            ).unwrap()
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        let expected = vec![("a".to_string(), "delta".to_string()), ("c".to_string(), "charlie".to_string())];
+        assert_eq!(indexed(&conn), expected);
+        let rows: u32 = conn.query_row("SELECT COUNT(*) FROM items_fts", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 2);
+        
+        // VACUUM 不改变显式保存的编号
+        conn.execute("VACUUM", []).unwrap();
+        assert_eq!(indexed(&conn), expected);
+    }
+    
+    #[test]
+    fn newer_database_is_refused() {
+        let (mut conn, path) = version_one_database();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        
+        let error = migrate(&mut conn, &path).unwrap_err();
+        assert!(error.to_string().contains(&(SCHEMA_VERSION + 1).to_string()), "{}", error);
+        
+        // 版本号和表结构都不变
+        assert_eq!(user_version(&conn), SCHEMA_VERSION + 1);
+        assert!(!column_exists(&conn.transaction().unwrap(), "clipboard_items", "content_hash").unwrap());
+    }
+}
@@ -0,0 +1,528 @@
+// 搜索查询语言
+//
+// 例如：type:image tag:work from:chrome after:2026-01-01 is:pinned -tag:temp "exact phrase"
+//       kind:url
+//       regex:"error \d+"
+//       after:this-week before:-2h
+//
+// 空格分隔的条件默认为 AND，支持 AND / OR / NOT（大写）、前缀 `-` 取反以及括号分组。
+// 未知的 `key:value`（如 URL）按普通文本处理。
+
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{regexp, ContentKind, ContentType, SearchQuery};
+
+/// 查询表达式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QueryExpr {
+    And(Vec<QueryExpr>),
+    Or(Vec<QueryExpr>),
+    Not(Box<QueryExpr>),
+    /// 普通文本
+    Text(String),
+    /// 引号中的完整短语
+    Phrase(String),
+    /// 对完整文本的正则匹配
+    Regex(String),
+    Tag(String),
+    Type(ContentType),
+    /// 捕获时识别出的文本类型
+    Kind(ContentKind),
+    /// 来源程序或窗口
+    Source(String),
+    After(DateTime<Utc>),
+    Before(DateTime<Utc>),
+    Pinned,
+    Favorite,
+}
+
+/// 查询解析错误，`position` 为出错位置（按字符计）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl fmt::Display for QueryParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}（位置 {}）", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QueryParseError {}
+
+impl SearchQuery {
+    /// 解析查询语言
+    ///
+    /// 顶层 AND 中的普通文本放入 `text` 以便按相关度排序，其余条件放入 `expr`。
+    pub fn parse(input: &str) -> Result<SearchQuery, QueryParseError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0, end: input.chars().count() };
+        
+        let mut query = SearchQuery {
+            input: Some(input.trim().to_string()),
+            ..SearchQuery::default()
+        };
+        if parser.tokens.is_empty() {
+            return Ok(query);
+        }
+        
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(error("多余的右括号", token.position));
+        }
+        
+        let terms = match expr {
+            QueryExpr::And(terms) => terms,
+            expr => vec![expr],
+        };
+        
+        let mut words = Vec::new();
+        let mut rest = Vec::new();
+        for term in terms {
+            match term {
+                QueryExpr::Text(word) => words.push(word),
+                term => rest.push(term),
+            }
+        }
+        
+        if !words.is_empty() {
+            query.text = Some(words.join(" "));
+        }
+        query.expr = match rest.len() {
+            0 => None,
+            1 => rest.pop(),
+            _ => Some(QueryExpr::And(rest)),
+        };
+        
+        Ok(query)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    Field(QueryExpr),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn error(message: impl Into<String>, position: usize) -> QueryParseError {
+    QueryParseError { message: message.into(), position }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        
+        let kind = match c {
+            '(' => {
+                i += 1;
+                TokenKind::LParen
+            }
+            ')' => {
+                i += 1;
+                TokenKind::RParen
+            }
+            // 只有紧跟条件的 `-` 表示取反
+            '-' if chars.get(i + 1).is_some_and(|next| !next.is_whitespace()) => {
+                i += 1;
+                TokenKind::Not
+            }
+            '"' => {
+                let (phrase, next) = read_quoted(&chars, i)?;
+                i = next;
+                TokenKind::Phrase(phrase)
+            }
+            _ => {
+                while i < chars.len() && !chars[i].is_whitespace() && !matches!(chars[i], '(' | ')' | '"') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                
+                // key:"带空格的值"
+                let quoted_value = if word.ends_with(':') && chars.get(i) == Some(&'"') {
+                    let (value, next) = read_quoted(&chars, i)?;
+                    let value_start = i;
+                    i = next;
+                    Some((value, value_start))
+                } else {
+                    None
+                };
+                
+                match word.as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => match word.split_once(':') {
+                        Some((key, value)) => {
+                            let (value, value_start) = match quoted_value {
+                                Some((value, value_start)) => (value, value_start),
+                                None => (value.to_string(), start + key.chars().count() + 1),
+                            };
+                            match parse_field(key, &value, value_start)? {
+                                Some(expr) => TokenKind::Field(expr),
+                                None => TokenKind::Word(word),
+                            }
+                        }
+                        None => TokenKind::Word(word),
+                    },
+                }
+            }
+        };
+        
+        tokens.push(Token { kind, position: start });
+    }
+    
+    Ok(tokens)
+}
+
+/// 读取 `start` 处开始的引号内容，返回内容和结束引号之后的位置
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QueryParseError> {
+    let end = chars[start + 1..].iter()
+        .position(|&c| c == '"')
+        .map(|offset| start + 1 + offset)
+        .ok_or_else(|| error("引号未闭合", start))?;
+    
+    Ok((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+/// 解析 `key:value`，未知的 key 返回 `None`
+fn parse_field(key: &str, value: &str, position: usize) -> Result<Option<QueryExpr>, QueryParseError> {
+    let key = key.to_lowercase();
+    let known = matches!(key.as_str(), "type" | "kind" | "tag" | "from" | "source" | "app" | "after" | "before" | "is" | "regex" | "re");
+    if !known {
+        return Ok(None);
+    }
+    
+    if value.is_empty() {
+        return Err(error(format!("{}: 缺少值", key), position));
+    }
+    
+    let expr = match key.as_str() {
+        "type" => QueryExpr::Type(match value.to_lowercase().as_str() {
+            "text" => ContentType::Text,
+            "image" | "img" => ContentType::Image,
+            "file" | "files" => ContentType::File,
+            "html" => ContentType::Html,
+            "richtext" | "rtf" => ContentType::RichText,
+            "custom" => ContentType::Custom,
+            _ => return Err(error(format!("未知的类型: {}", value), position)),
+        }),
+        "kind" => QueryExpr::Kind(
+            ContentKind::parse(value).ok_or_else(|| error(format!("未知的内容类型: {}", value), position))?
+        ),
+        "tag" => QueryExpr::Tag(value.to_string()),
+        "regex" | "re" => {
+            regexp::compile(value).map_err(|message| error(message, position))?;
+            QueryExpr::Regex(value.to_string())
+        }
+        "from" | "source" | "app" => QueryExpr::Source(value.to_string()),
+        "after" => QueryExpr::After(parse_date(value, position)?),
+        "before" => QueryExpr::Before(parse_date(value, position)?),
+        "is" => match value.to_lowercase().as_str() {
+            "pinned" => QueryExpr::Pinned,
+            "favorite" | "fav" | "starred" => QueryExpr::Favorite,
+            _ => return Err(error(format!("未知的状态: {}", value), position)),
+        },
+        _ => unreachable!(),
+    };
+    
+    Ok(Some(expr))
+}
+
+/// 日期按本地时间的零点计算，也接受 RFC 3339 时间
+///
+/// 相对日期 `today`、`yesterday`、`this-week`、`this-month`、`this-year` 和
+/// `-7d`（单位 `h`、`d`、`w`）按解析时的当前时间计算。
+fn parse_date(value: &str, position: usize) -> Result<DateTime<Utc>, QueryParseError> {
+    let today = Local::now().date_naive();
+    let date = match value.to_ascii_lowercase().as_str() {
+        "today" => Some(today),
+        "yesterday" => today.pred_opt(),
+        "this-week" => Some(today - chrono::Duration::days(i64::from(today.weekday().num_days_from_monday()))),
+        "this-month" => today.with_day(1),
+        "this-year" => today.with_ordinal(1),
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+    };
+    if let Some(date) = date {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+        if let Some(local) = Local.from_local_datetime(&midnight).earliest() {
+            return Ok(local.with_timezone(&Utc));
+        }
+    }
+    
+    if let Some(time) = relative_time(value) {
+        return Ok(time);
+    }
+    
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| error(format!("无效的日期: {}（应为 YYYY-MM-DD、today、this-week 或 -7d 等）", value), position))
+}
+
+// `-12h`、`-7d`、`-2w`：当前时间之前的一段时间
+fn relative_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.strip_prefix('-')?;
+    let unit = value.chars().last()?;
+    let amount: u32 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    let offset = match unit {
+        'h' => chrono::Duration::hours(i64::from(amount)),
+        'd' => chrono::Duration::days(i64::from(amount)),
+        'w' => chrono::Duration::weeks(i64::from(amount)),
+        _ => return None,
+    };
+    Utc::now().checked_sub_signed(offset)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // 输入末尾的位置，用于报告缺少条件的错误
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    
+    fn current_position(&self) -> usize {
+        self.peek().map_or(self.end, |token| token.position)
+    }
+    
+    fn parse_or(&mut self) -> Result<QueryExpr, QueryParseError> {
+        let mut terms = vec![self.parse_and()?];
+        
+        while self.peek().is_some_and(|token| token.kind == TokenKind::Or) {
+            self.next();
+            terms.push(self.parse_and()?);
+        }
+        
+        Ok(if terms.len() == 1 { terms.remove(0) } else { QueryExpr::Or(terms) })
+    }
+    
+    fn parse_and(&mut self) -> Result<QueryExpr, QueryParseError> {
+        let mut terms = vec![self.parse_unary()?];
+        
+        loop {
+            match self.peek().map(|token| &token.kind) {
+                None | Some(TokenKind::RParen) | Some(TokenKind::Or) => break,
+                Some(TokenKind::And) => {
+                    self.next();
+                }
+                _ => {}
+            }
+            terms.push(self.parse_unary()?);
+        }
+        
+        Ok(if terms.len() == 1 { terms.remove(0) } else { QueryExpr::And(terms) })
+    }
+    
+    fn parse_unary(&mut self) -> Result<QueryExpr, QueryParseError> {
+        if self.peek().is_some_and(|token| token.kind == TokenKind::Not) {
+            self.next();
+            return Ok(QueryExpr::Not(Box::new(self.parse_unary()?)));
+        }
+        
+        self.parse_primary()
+    }
+    
+    fn parse_primary(&mut self) -> Result<QueryExpr, QueryParseError> {
+        let position = self.current_position();
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(error("缺少条件", position)),
+        };
+        
+        match token.kind {
+            TokenKind::LParen => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RParen, .. }) => Ok(expr),
+                    _ => Err(error("括号未闭合", token.position)),
+                }
+            }
+            TokenKind::RParen => Err(error("缺少条件", token.position)),
+            TokenKind::And => Err(error("AND 前缺少条件", token.position)),
+            TokenKind::Or => Err(error("OR 前缺少条件", token.position)),
+            TokenKind::Not => unreachable!(),
+            TokenKind::Word(word) => Ok(QueryExpr::Text(word)),
+            TokenKind::Phrase(phrase) => Ok(QueryExpr::Phrase(phrase)),
+            TokenKind::Field(expr) => Ok(expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn text(word: &str) -> QueryExpr {
+        QueryExpr::Text(word.to_string())
+    }
+    
+    fn tag(name: &str) -> QueryExpr {
+        QueryExpr::Tag(name.to_string())
+    }
+    
+    fn local_midnight(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Local.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap().with_timezone(&Utc)
+    }
+    
+    fn parse_error(input: &str) -> QueryParseError {
+        SearchQuery::parse(input).unwrap_err()
+    }
+    
+    #[test]
+    fn relative_dates_are_resolved_when_parsed() {
+        let after = |input: &str| match SearchQuery::parse(input).unwrap().expr {
+            Some(QueryExpr::After(time)) => time,
+            expr => panic!("unexpected expr: {:?}", expr),
+        };
+        
+        let week_ago = Utc::now() - chrono::Duration::days(7);
+        assert!((after("after:-7d") - week_ago).num_seconds().abs() <= 1);
+        assert!((after("after:-2w") - (week_ago - chrono::Duration::days(7))).num_seconds().abs() <= 1);
+        
+        let today = Local::now().date_naive();
+        let this_week = after("after:This-Week").with_timezone(&Local);
+        assert_eq!(this_week.weekday(), chrono::Weekday::Mon);
+        assert!(this_week.date_naive() <= today && today - this_week.date_naive() < chrono::Duration::days(7));
+        assert_eq!(after("after:today").with_timezone(&Local).date_naive(), today);
+        assert_eq!(after("after:this-month").with_timezone(&Local).day(), 1);
+        
+        for invalid in ["after:-7", "after:-7y", "after:--7d", "after:-99999999999w"] {
+            assert!(parse_error(invalid).message.contains("无效的日期"), "{}", invalid);
+        }
+    }
+    
+    #[test]
+    fn parses_the_documented_example() {
+        let query = SearchQuery::parse(
+            r#"type:image tag:work from:chrome after:2026-01-01 is:pinned -tag:temp "exact phrase""#
+        ).unwrap();
+        
+        assert_eq!(query.text, None);
+        assert_eq!(query.expr, Some(QueryExpr::And(vec![
+            QueryExpr::Type(ContentType::Image),
+            tag("work"),
+            QueryExpr::Source("chrome".to_string()),
+            QueryExpr::After(local_midnight(2026, 1, 1)),
+            QueryExpr::Pinned,
+            QueryExpr::Not(Box::new(tag("temp"))),
+            QueryExpr::Phrase("exact phrase".to_string()),
+        ])));
+    }
+    
+    #[test]
+    fn top_level_words_become_search_text() {
+        let query = SearchQuery::parse("  hello tag:work world ").unwrap();
+        assert_eq!(query.input.as_deref(), Some("hello tag:work world"));
+        assert_eq!(query.text.as_deref(), Some("hello world"));
+        assert_eq!(query.expr, Some(tag("work")));
+        
+        // 未知的 key 按普通文本处理
+        let query = SearchQuery::parse("https://example.com").unwrap();
+        assert_eq!(query.text.as_deref(), Some("https://example.com"));
+        assert_eq!(query.expr, None);
+        
+        assert_eq!(SearchQuery::parse("   ").unwrap().expr, None);
+    }
+    
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let query = SearchQuery::parse("a b OR c AND d").unwrap();
+        assert_eq!(query.text, None);
+        assert_eq!(query.expr, Some(QueryExpr::Or(vec![
+            QueryExpr::And(vec![text("a"), text("b")]),
+            QueryExpr::And(vec![text("c"), text("d")]),
+        ])));
+        
+        let query = SearchQuery::parse("(a OR b) c").unwrap();
+        assert_eq!(query.text.as_deref(), Some("c"));
+        assert_eq!(query.expr, Some(QueryExpr::Or(vec![text("a"), text("b")])));
+    }
+    
+    #[test]
+    fn negation_applies_to_the_next_term() {
+        let query = SearchQuery::parse("NOT a b").unwrap();
+        assert_eq!(query.text.as_deref(), Some("b"));
+        assert_eq!(query.expr, Some(QueryExpr::Not(Box::new(text("a")))));
+        
+        let query = SearchQuery::parse("-(tag:a OR tag:b)").unwrap();
+        assert_eq!(query.expr, Some(QueryExpr::Not(Box::new(QueryExpr::Or(vec![tag("a"), tag("b")])))));
+        
+        // 后面跟空白的 `-` 只是普通文本
+        let query = SearchQuery::parse("a - b").unwrap();
+        assert_eq!(query.text.as_deref(), Some("a - b"));
+    }
+    
+    #[test]
+    fn quoted_field_values_may_contain_spaces() {
+        let query = SearchQuery::parse(r#"tag:"my work" from:"Visual Studio""#).unwrap();
+        assert_eq!(query.expr, Some(QueryExpr::And(vec![
+            tag("my work"),
+            QueryExpr::Source("Visual Studio".to_string()),
+        ])));
+    }
+    
+    #[test]
+    fn errors_report_character_positions() {
+        let error = parse_error(r#"foo "bar"#);
+        assert_eq!(error.position, 4);
+        assert_eq!(error.message, "引号未闭合");
+        
+        assert_eq!(parse_error(r#"tag:"work"#).position, 4);
+        assert_eq!(parse_error("after:2026-13-01").position, 6);
+        assert_eq!(parse_error("before:tomorrow").position, 7);
+        // 位置按字符而不是字节计算
+        assert_eq!(parse_error("中文 after:bad").position, 9);
+        assert_eq!(parse_error("is:unknown").position, 3);
+        assert_eq!(parse_error("type:").position, 5);
+        
+        assert_eq!(parse_error("(a OR b").position, 0);
+        assert_eq!(parse_error("a )").position, 2);
+        assert_eq!(parse_error("a OR").position, 4);
+        assert_eq!(parse_error("OR a").position, 0);
+        assert_eq!(parse_error("a AND OR b").position, 6);
+    }
+    
+    #[test]
+    fn dates_accept_rfc3339() {
+        let query = SearchQuery::parse("before:2026-01-01T12:00:00Z").unwrap();
+        assert_eq!(
+            query.expr,
+            Some(QueryExpr::Before(Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()))
+        );
+    }
+}
@@ -0,0 +1,168 @@
+// 正则搜索
+//
+// 向 SQLite 注册 `REGEXP` 函数，`X REGEXP Y` 即 `regexp(Y, X)`。
+// regex 库保证线性时间匹配，不会出现回溯爆炸；这里再限制表达式长度、
+// 编译后的大小、单条文本的匹配长度以及单次查询的总耗时，避免卡住界面。
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use regex::bytes::{Regex, RegexBuilder};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+
+/// 表达式的最大长度（按字符计）
+pub const MAX_PATTERN_LEN: usize = 1000;
+/// 编译后的最大大小
+const SIZE_LIMIT: usize = 1 << 20;
+/// 每条文本只匹配前 1 MB
+const MAX_TEXT_LEN: usize = 1 << 20;
+/// 单次查询中正则匹配的总耗时上限
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// 缓存的表达式数量上限
+const CACHE_SIZE: usize = 32;
+
+lazy_static! {
+    static ref CACHE: Mutex<HashMap<String, Arc<Regex>>> = Mutex::new(HashMap::new());
+    static ref TEXT_CACHE: Mutex<HashMap<String, Arc<regex::Regex>>> = Mutex::new(HashMap::new());
+}
+
+/// 编译表达式，相同的表达式只编译一次
+pub fn compile(pattern: &str) -> Result<Arc<Regex>, String> {
+    cached(&CACHE, pattern, |pattern| {
+        RegexBuilder::new(pattern)
+            .size_limit(SIZE_LIMIT)
+            .dfa_size_limit(SIZE_LIMIT)
+            .build()
+    })
+}
+
+/// 编译只用于 UTF-8 文本的表达式，匹配范围总在字符边界上
+///
+/// 可能匹配到半个字符的表达式（如 `(?-u).`）会编译失败；需要按范围截取文本时使用。
+pub fn compile_text(pattern: &str) -> Result<Arc<regex::Regex>, String> {
+    cached(&TEXT_CACHE, pattern, |pattern| {
+        regex::RegexBuilder::new(pattern)
+            .size_limit(SIZE_LIMIT)
+            .dfa_size_limit(SIZE_LIMIT)
+            .build()
+    })
+}
+
+fn cached<R>(
+    cache: &Mutex<HashMap<String, Arc<R>>>,
+    pattern: &str,
+    build: impl FnOnce(&str) -> Result<R, regex::Error>,
+) -> Result<Arc<R>, String> {
+    if let Some(regex) = cache.lock().get(pattern) {
+        return Ok(regex.clone());
+    }
+    
+    if pattern.chars().count() > MAX_PATTERN_LEN {
+        return Err(format!("正则表达式过长（最多 {} 个字符）", MAX_PATTERN_LEN));
+    }
+    
+    let regex = build(pattern)
+        .map(Arc::new)
+        .map_err(|e| format!("无效的正则表达式: {}", e))?;
+    
+    let mut cache = cache.lock();
+    if cache.len() >= CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), regex.clone());
+    
+    Ok(regex)
+}
+
+// 同一条语句内共享的编译结果和截止时间
+struct Matcher {
+    regex: Arc<Regex>,
+    deadline: Instant,
+}
+
+/// 在连接上注册 `REGEXP` 函数
+///
+/// 超时后返回错误，结果与执行时间有关，因此不能标记为 `SQLITE_DETERMINISTIC`。
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    register_with_timeout(conn, TIMEOUT)
+}
+
+fn register_with_timeout(conn: &Connection, timeout: Duration) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8,
+        move |ctx| {
+            let matcher = ctx.get_or_create_aux(0, |pattern| -> Result<Matcher, String> {
+                let pattern = pattern.as_str().map_err(|_| "正则表达式必须是文本".to_string())?;
+                Ok(Matcher {
+                    regex: compile(pattern)?,
+                    deadline: Instant::now() + timeout,
+                })
+            })?;
+            
+            if Instant::now() >= matcher.deadline {
+                return Err(rusqlite::Error::UserFunctionError("正则搜索超时".into()));
+            }
+            
+            let text = match ctx.get_raw(1) {
+                ValueRef::Text(text) | ValueRef::Blob(text) => text,
+                _ => return Ok(false),
+            };
+            let text = &text[..text.len().min(MAX_TEXT_LEN)];
+            
+            Ok(matcher.regex.is_match(text))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn matches(conn: &Connection, text: &str, pattern: &str) -> rusqlite::Result<bool> {
+        conn.query_row("SELECT ? REGEXP ?", [text, pattern], |row| row.get(0))
+    }
+    
+    #[test]
+    fn patterns_are_compiled_once() {
+        let first = compile(r"cache-test-\d+").unwrap();
+        let second = compile(r"cache-test-\d+").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+    
+    #[test]
+    fn invalid_and_oversized_patterns_are_errors() {
+        assert!(compile("(unclosed").unwrap_err().contains("无效的正则表达式"));
+        assert!(compile(&"a".repeat(MAX_PATTERN_LEN + 1)).unwrap_err().contains("过长"));
+        // 编译结果超过大小限制的表达式在编译时被拒绝
+        assert!(compile(r"(\w{100}){100}").is_err());
+        
+        let conn = Connection::open_in_memory().unwrap();
+        register(&conn).unwrap();
+        assert!(matches(&conn, "text", "(unclosed").is_err());
+        assert!(matches(&conn, "error 42", r"error \d+").unwrap());
+        assert!(!matches(&conn, "error", r"error \d+").unwrap());
+    }
+    
+    #[test]
+    fn queries_past_the_deadline_are_aborted() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_with_timeout(&conn, Duration::ZERO).unwrap();
+        
+        let error = conn.query_row(
+            "SELECT COUNT(*) FROM (
+                WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100)
+                SELECT i FROM n
+             ) WHERE 'row ' || i REGEXP 'row \\d+'",
+            [],
+            |row| row.get::<_, i64>(0),
+        ).unwrap_err();
+        assert!(error.to_string().contains("超时"), "{}", error);
+    }
+}
@@ -0,0 +1,413 @@
+// 自动标签规则
+//
+// 规则保存在设置中，每条新捕获的条目都会按顺序匹配全部规则，命中的规则
+// 依次添加标签、收藏/置顶标记和元数据。规则中列出的条件需要全部满足，
+// 同一条件中的多个值满足其一即可。
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{regexp, tags, ClipboardContent, ClipboardItem, ContentType, Database};
+
+lazy_static! {
+    static ref URL_HOST: Regex = Regex::new(r"(?i)\b(?:https?|ftp)://(?:[^/\s@]*@)?([a-z0-9.\-]+)").unwrap();
+}
+
+/// 自动标签规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagRule {
+    pub name: String,
+    #[serde(default = "crate::default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub conditions: RuleConditions,
+    #[serde(default)]
+    pub actions: RuleActions,
+}
+
+/// 规则的匹配条件，未设置的条件不参与匹配
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleConditions {
+    pub content_types: Vec<ContentType>,
+    /// 对完整文本的正则匹配
+    pub pattern: Option<String>,
+    /// 来源程序的正则匹配，不区分大小写
+    pub source_app: Option<String>,
+    /// 来源窗口类的正则匹配，不区分大小写
+    pub source_window: Option<String>,
+    /// 文本中链接的域名，`github.com` 同时匹配其子域名
+    pub url_domains: Vec<String>,
+    /// 文件扩展名，不含 `.`，不区分大小写
+    pub file_extensions: Vec<String>,
+}
+
+/// 规则命中后对条目的修改
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleActions {
+    pub tags: Vec<String>,
+    pub favorite: bool,
+    pub pinned: bool,
+    pub metadata: HashMap<String, String>,
+}
+
+impl TagRule {
+    /// 检查规则是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("规则名称不能为空".to_string());
+        }
+        
+        let conditions = &self.conditions;
+        let has_condition = !conditions.content_types.is_empty()
+            || conditions.pattern.is_some()
+            || conditions.source_app.is_some()
+            || conditions.source_window.is_some()
+            || !conditions.url_domains.is_empty()
+            || !conditions.file_extensions.is_empty();
+        if !has_condition {
+            return Err(format!("规则 {} 没有任何条件", self.name));
+        }
+        
+        for pattern in [&conditions.pattern, &conditions.source_app, &conditions.source_window].into_iter().flatten() {
+            regexp::compile(pattern).map_err(|e| format!("规则 {}: {}", self.name, e))?;
+        }
+        
+        let actions = &self.actions;
+        if actions.tags.iter().any(|tag| tags::normalize_tag(tag).is_none()) {
+            return Err(format!("规则 {} 包含空标签", self.name));
+        }
+        if actions.tags.is_empty() && !actions.favorite && !actions.pinned && actions.metadata.is_empty() {
+            return Err(format!("规则 {} 没有任何动作", self.name));
+        }
+        
+        Ok(())
+    }
+    
+    /// 条目是否满足规则的全部条件，无效或停用的规则不匹配任何条目
+    pub fn matches(&self, item: &ClipboardItem) -> bool {
+        if !self.enabled || self.validate().is_err() {
+            return false;
+        }
+        
+        let conditions = &self.conditions;
+        if !conditions.content_types.is_empty() && !conditions.content_types.contains(&ContentType::of(&item.content)) {
+            return false;
+        }
+        
+        if let Some(pattern) = &conditions.pattern {
+            let text = Database::search_text(&item.representations());
+            if !is_match(pattern, &text, false) {
+                return false;
+            }
+        }
+        
+        if let Some(pattern) = &conditions.source_app {
+            if !is_match(pattern, item.source_app.as_deref().unwrap_or_default(), true) {
+                return false;
+            }
+        }
+        
+        if let Some(pattern) = &conditions.source_window {
+            if !is_match(pattern, item.source_window.as_deref().unwrap_or_default(), true) {
+                return false;
+            }
+        }
+        
+        if !conditions.url_domains.is_empty() {
+            let hosts = url_hosts(item);
+            let matched = conditions.url_domains.iter().any(|domain| {
+                let domain = domain.trim().trim_start_matches('.').to_lowercase();
+                hosts.iter().any(|host| host == &domain || host.ends_with(&format!(".{}", domain)))
+            });
+            if !matched {
+                return false;
+            }
+        }
+        
+        if !conditions.file_extensions.is_empty() {
+            let matched = file_extensions(item).iter().any(|extension| {
+                conditions.file_extensions.iter()
+                    .any(|wanted| wanted.trim_start_matches('.').eq_ignore_ascii_case(extension))
+            });
+            if !matched {
+                return false;
+            }
+        }
+        
+        true
+    }
+    
+    /// 对条目执行规则的动作，返回条目是否有变化
+    pub fn apply(&self, item: &mut ClipboardItem) -> bool {
+        let actions = &self.actions;
+        let mut changed = false;
+        
+        for tag in actions.tags.iter().filter_map(|tag| tags::normalize_tag(tag)) {
+            if !item.tags.contains(&tag) {
+                item.tags.push(tag);
+                changed = true;
+            }
+        }
+        
+        if actions.favorite && !item.favorite {
+            item.favorite = true;
+            changed = true;
+        }
+        if actions.pinned && !item.pinned {
+            item.pinned = true;
+            changed = true;
+        }
+        
+        for (key, value) in &actions.metadata {
+            if item.metadata.get(key) != Some(value) {
+                item.metadata.insert(key.clone(), value.clone());
+                changed = true;
+            }
+        }
+        
+        changed
+    }
+}
+
+/// 按顺序对条目执行全部命中的规则，返回条目是否有变化
+pub fn apply_rules(rules: &[TagRule], item: &mut ClipboardItem) -> bool {
+    let mut changed = false;
+    for rule in rules {
+        if rule.matches(item) {
+            changed |= rule.apply(item);
+        }
+    }
+    
+    changed
+}
+
+impl Database {
+    /// 对已有的全部条目重新执行规则，返回修改的条目数
+    ///
+    /// 逐条读取条目，只写入规则可能修改的标签、收藏/置顶标记和元数据。
+    pub fn apply_tag_rules(&self, rules: &[TagRule]) -> Result<u32, Box<dyn std::error::Error>> {
+        let ids: Vec<String> = {
+            let conn = self.conn.lock();
+            let mut stmt = conn.prepare("SELECT id FROM clipboard_items")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        
+        let mut changed = 0;
+        for id in ids.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+            let mut item = match self.get_item(id)? {
+                Some(item) => item,
+                None => continue,
+            };
+            if apply_rules(rules, &mut item) {
+                self.write_rule_changes(&item)?;
+                changed += 1;
+            }
+        }
+        
+        Ok(changed)
+    }
+    
+    fn write_rule_changes(&self, item: &ClipboardItem) -> Result<(), Box<dyn std::error::Error>> {
+        let id = item.id.to_string();
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        Self::rewrite_tags(&tx, &id, |_| item.tags.clone())?;
+        
+        tx.execute(
+            "UPDATE clipboard_items SET favorite = ?, pinned = ?, metadata_json = ?, updated_at = strftime('%s', 'now') WHERE id = ?",
+            params![item.favorite as i32, item.pinned as i32, serde_json::to_string(&item.metadata)?, id],
+        )?;
+        tx.execute("DELETE FROM item_metadata WHERE item_id = ?", params![id])?;
+        for (key, value) in &item.metadata {
+            tx.execute(
+                "INSERT INTO item_metadata (item_id, key, value) VALUES (?, ?, ?)",
+                params![id, key, value],
+            )?;
+        }
+        
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn is_match(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    let pattern = if ignore_case { format!("(?i){}", pattern) } else { pattern.to_string() };
+    regexp::compile(&pattern).is_ok_and(|regex| regex.is_match(text.as_bytes()))
+}
+
+// 文本和来源地址中链接的域名（小写）
+fn url_hosts(item: &ClipboardItem) -> Vec<String> {
+    let text = Database::search_text(&item.representations());
+    let source_url = item.metadata.get("source_url").map(String::as_str).unwrap_or_default();
+    
+    [text.as_str(), source_url].iter()
+        .flat_map(|text| URL_HOST.captures_iter(text))
+        .map(|captures| captures[1].trim_end_matches('.').to_lowercase())
+        .collect()
+}
+
+fn file_extensions(item: &ClipboardItem) -> Vec<String> {
+    item.representations().iter()
+        .filter_map(|content| match content {
+            ClipboardContent::FileList(files) => Some(files),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|file| file.path.extension())
+        .map(|extension| extension.to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use chrono::Utc;
+    use crate::{DuplicatePolicy, FileItem};
+    
+    fn rule(conditions: RuleConditions, tags: &[&str]) -> TagRule {
+        TagRule {
+            name: "rule".to_string(),
+            enabled: true,
+            conditions,
+            actions: RuleActions {
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..RuleActions::default()
+            },
+        }
+    }
+    
+    #[test]
+    fn conditions_must_all_match() {
+        let code = rule(RuleConditions {
+            content_types: vec![ContentType::Text],
+            pattern: Some(r"fn \w+\(".to_string()),
+            source_app: Some("^code$".to_string()),
+            source_window: Some("chrome_widget".to_string()),
+            ..RuleConditions::default()
+        }, &["code"]);
+        
+        let mut matching = ClipboardItem::test_text("fn main() {}");
+        matching.source_app = Some("Code".to_string());
+        matching.source_window = Some("Chrome_WidgetWin_1".to_string());
+        assert!(code.matches(&matching));
+        
+        let mut other_window = matching.clone();
+        other_window.source_window = Some("ConsoleWindowClass".to_string());
+        assert!(!code.matches(&other_window));
+        
+        let mut other_app = matching.clone();
+        other_app.source_app = None;
+        assert!(!code.matches(&other_app));
+        
+        // 正则匹配内容时区分大小写
+        let mut other_text = matching.clone();
+        other_text.content = ClipboardContent::Text("FN main() {}".to_string());
+        assert!(!code.matches(&other_text));
+    }
+    
+    #[test]
+    fn url_domains_include_subdomains() {
+        let github = rule(RuleConditions { url_domains: vec!["github.com".to_string()], ..RuleConditions::default() }, &["github"]);
+        
+        assert!(github.matches(&ClipboardItem::test_text("see https://github.com/rust-lang/rust")));
+        assert!(github.matches(&ClipboardItem::test_text("https://gist.GitHub.com/abc")));
+        assert!(!github.matches(&ClipboardItem::test_text("https://notgithub.com/abc")));
+        assert!(!github.matches(&ClipboardItem::test_text("github.com without a scheme")));
+        
+        // HTML 的来源地址同样参与匹配
+        let mut html = ClipboardItem::test_item(ClipboardContent::Html("<p>snippet</p>".to_string()));
+        html.metadata.insert("source_url".to_string(), "https://docs.github.com/en".to_string());
+        assert!(github.matches(&html));
+    }
+    
+    #[test]
+    fn file_extensions_ignore_case_and_dot() {
+        let images = rule(RuleConditions { file_extensions: vec![".png".to_string()], ..RuleConditions::default() }, &["images"]);
+        let file = |path: &str| FileItem {
+            path: PathBuf::from(path),
+            size: 0,
+            modified: Utc::now(),
+            is_dir: false,
+            mime_type: None,
+            exists: true,
+        };
+        
+        assert!(images.matches(&ClipboardItem::test_item(ClipboardContent::FileList(vec![file("a.txt"), file("b.PNG")]))));
+        assert!(!images.matches(&ClipboardItem::test_item(ClipboardContent::FileList(vec![file("a.txt")]))));
+        assert!(!images.matches(&ClipboardItem::test_text("b.png")));
+    }
+    
+    #[test]
+    fn invalid_and_disabled_rules_never_match() {
+        assert!(rule(RuleConditions::default(), &["tag"]).validate().is_err());
+        assert!(rule(RuleConditions { pattern: Some("x".to_string()), ..RuleConditions::default() }, &[]).validate().is_err());
+        assert!(rule(RuleConditions { pattern: Some("x".to_string()), ..RuleConditions::default() }, &[" / "]).validate().is_err());
+        
+        let invalid = rule(RuleConditions { pattern: Some("(".to_string()), ..RuleConditions::default() }, &["tag"]);
+        assert!(invalid.validate().is_err());
+        assert!(!invalid.matches(&ClipboardItem::test_text("(")));
+        
+        let mut disabled = rule(RuleConditions { pattern: Some("x".to_string()), ..RuleConditions::default() }, &["tag"]);
+        disabled.enabled = false;
+        assert!(!disabled.matches(&ClipboardItem::test_text("x")));
+    }
+    
+    #[test]
+    fn rules_apply_actions_in_order() {
+        let mut favorite = rule(RuleConditions { pattern: Some("todo".to_string()), ..RuleConditions::default() }, &["work / todo"]);
+        favorite.actions.favorite = true;
+        favorite.actions.metadata.insert("list".to_string(), "inbox".to_string());
+        let pinned = rule(RuleConditions { pattern: Some("urgent".to_string()), ..RuleConditions::default() }, &["work/todo", "urgent"]);
+        let rules = [favorite, pinned];
+        
+        let mut matching = ClipboardItem::test_text("urgent todo");
+        assert!(apply_rules(&rules, &mut matching));
+        assert_eq!(matching.tags, ["work/todo", "urgent"]);
+        assert!(matching.favorite);
+        assert_eq!(matching.metadata["list"], "inbox");
+        
+        // 再次执行不会产生变化
+        assert!(!apply_rules(&rules, &mut matching));
+        assert!(!apply_rules(&rules, &mut ClipboardItem::test_text("done")));
+    }
+    
+    #[test]
+    fn reapplying_rules_only_updates_matching_items() {
+        let database = Database::new(":memory:").unwrap();
+        let todo = ClipboardItem::test_text("todo: ship it");
+        let other = ClipboardItem::test_text("lunch");
+        for item in [&todo, &other] {
+            database.save_item(item.clone(), DuplicatePolicy::KeepBoth, 0).unwrap();
+        }
+        
+        let mut rule = rule(RuleConditions { pattern: Some("todo".to_string()), ..RuleConditions::default() }, &["todo"]);
+        rule.actions.pinned = true;
+        rule.actions.metadata.insert("list".to_string(), "inbox".to_string());
+        let rules = [rule];
+        
+        assert_eq!(database.apply_tag_rules(&rules).unwrap(), 1);
+        assert_eq!(database.apply_tag_rules(&rules).unwrap(), 0);
+        
+        let stored = database.get_item(todo.id).unwrap().unwrap();
+        assert_eq!(stored.tags, ["todo"]);
+        assert!(stored.pinned);
+        assert_eq!(stored.metadata["list"], "inbox");
+        assert!(matches!(&stored.content, ClipboardContent::Text(text) if text == "todo: ship it"));
+        assert!(database.get_item(other.id).unwrap().unwrap().tags.is_empty());
+        
+        // 标签表和全文索引同步更新
+        assert_eq!(database.get_tags().unwrap().iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>(), ["todo"]);
+        let query = crate::SearchQuery { expr: crate::SearchQuery::parse("tag:todo").unwrap().expr, ..Default::default() };
+        assert_eq!(database.search_items(query, &crate::SearchSettings::default()).unwrap().len(), 1);
+    }
+}
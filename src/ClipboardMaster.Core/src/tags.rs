@@ -0,0 +1,288 @@
+// 标签管理
+//
+// 标签可以用 `/` 表示层级，例如 `work/projectA` 属于 `work`。标签同时保存在
+// `clipboard_items.tags_json`（全文索引由触发器同步）和 `item_tags` 中，
+// 这里的修改都在同一个事务中更新两处。
+
+use std::collections::{BTreeMap, HashSet};
+
+use rusqlite::{params, Transaction};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Database;
+
+/// 标签及使用数量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagInfo {
+    pub name: String,
+    /// 直接使用该标签的条目数
+    pub count: u32,
+    /// 包含子标签在内的条目数
+    pub total_count: u32,
+}
+
+/// 规范化标签：去掉每一级首尾的空白和空的层级，结果为空时返回 `None`
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let parts: Vec<&str> = tag.split('/')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("/"))
+    }
+}
+
+/// `tag` 是否为 `ancestor` 本身或其子标签
+pub fn is_tag_or_descendant(tag: &str, ancestor: &str) -> bool {
+    tag == ancestor
+        || tag.strip_prefix(ancestor).is_some_and(|rest| rest.starts_with('/'))
+}
+
+impl Database {
+    /// 全部标签及使用数量，按名称排序；只作为上级出现的标签数量为 0
+    pub fn get_tags(&self) -> Result<Vec<TagInfo>, Box<dyn std::error::Error>> {
+        let counts: Vec<(String, u32)> = {
+            let conn = self.conn.lock();
+            let mut stmt = conn.prepare("SELECT tag, COUNT(*) FROM item_tags GROUP BY tag")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        
+        let mut tags: BTreeMap<String, TagInfo> = BTreeMap::new();
+        for (name, count) in &counts {
+            let mut ancestor = String::new();
+            for part in name.split('/') {
+                if !ancestor.is_empty() {
+                    ancestor.push('/');
+                }
+                ancestor.push_str(part);
+                
+                tags.entry(ancestor.clone()).or_insert_with(|| TagInfo {
+                    name: ancestor.clone(),
+                    count: 0,
+                    total_count: 0,
+                });
+            }
+            if let Some(info) = tags.get_mut(name) {
+                info.count = *count;
+            }
+        }
+        
+        // 同一条目可能同时带有上级和子标签，总数需要按条目去重
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare_cached(
+            "SELECT COUNT(DISTINCT item_id) FROM item_tags WHERE tag = ? OR substr(tag, 1, ?) = ?"
+        )?;
+        for info in tags.values_mut() {
+            let prefix = format!("{}/", info.name);
+            info.total_count = stmt.query_row(
+                params![info.name, prefix.chars().count() as i64, prefix],
+                |row| row.get(0),
+            )?;
+        }
+        
+        Ok(tags.into_values().collect())
+    }
+    
+    /// 重命名标签，子标签一并重命名（`work` 改为 `job` 时 `work/a` 变为 `job/a`），返回修改的条目数
+    ///
+    /// 新名称已存在时两者合并。
+    pub fn rename_tag(&self, old: &str, new: &str) -> Result<u32, Box<dyn std::error::Error>> {
+        self.merge_tags(&[old.to_string()], new)
+    }
+    
+    /// 将 `sources` 及其子标签合并到 `target`，返回修改的条目数
+    pub fn merge_tags(&self, sources: &[String], target: &str) -> Result<u32, Box<dyn std::error::Error>> {
+        let target = normalize_tag(target).ok_or("标签不能为空")?;
+        let sources: Vec<String> = sources.iter().filter_map(|tag| normalize_tag(tag)).collect();
+        
+        if sources.iter().any(|source| is_tag_or_descendant(&target, source) && target != *source) {
+            return Err("不能将标签合并到自己的子标签中".into());
+        }
+        
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        
+        // 同一条目可能带有多个被合并的标签，按条目计数
+        let mut changed = HashSet::new();
+        for source in &sources {
+            let prefix = format!("{}/", source);
+            let ids: Vec<String> = tx
+                .prepare("SELECT DISTINCT item_id FROM item_tags WHERE tag = ? OR substr(tag, 1, ?) = ?")?
+                .query_map(params![source, prefix.chars().count() as i64, prefix], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            
+            for id in ids {
+                let updated = Self::rewrite_tags(&tx, &id, |tags| {
+                    tags.iter()
+                        .map(|tag| match tag.strip_prefix(source.as_str()) {
+                            Some(rest) if is_tag_or_descendant(tag, source) => format!("{}{}", target, rest),
+                            _ => tag.clone(),
+                        })
+                        .collect()
+                })?;
+                if updated {
+                    changed.insert(id);
+                }
+            }
+        }
+        
+        tx.commit()?;
+        Ok(changed.len() as u32)
+    }
+    
+    /// 为条目添加标签
+    pub fn add_tags(&self, id: Uuid, tags: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let tags: Vec<String> = tags.iter().filter_map(|tag| normalize_tag(tag)).collect();
+        
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        Self::rewrite_tags(&tx, &id.to_string(), |current| {
+            current.iter().cloned().chain(tags.iter().cloned()).collect()
+        })?;
+        tx.commit()?;
+        
+        Ok(())
+    }
+    
+    /// 移除条目的标签
+    pub fn remove_tags(&self, id: Uuid, tags: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let tags: Vec<String> = tags.iter().filter_map(|tag| normalize_tag(tag)).collect();
+        
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction()?;
+        Self::rewrite_tags(&tx, &id.to_string(), |current| {
+            current.iter().filter(|tag| !tags.contains(tag)).cloned().collect()
+        })?;
+        tx.commit()?;
+        
+        Ok(())
+    }
+    
+    // 用 `f` 修改条目的标签并同步写入 tags_json 和 item_tags，重复的标签只保留一个
+    pub(crate) fn rewrite_tags(
+        tx: &Transaction,
+        id: &str,
+        f: impl FnOnce(&[String]) -> Vec<String>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let tags_json: String = tx.query_row(
+            "SELECT tags_json FROM clipboard_items WHERE id = ?",
+            params![id],
+            |row| row.get(0),
+        ).map_err(|_| format!("条目不存在: {}", id))?;
+        let current: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
+        
+        let mut tags: Vec<String> = Vec::new();
+        for tag in f(&current) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        if tags == current {
+            return Ok(false);
+        }
+        
+        tx.execute(
+            "UPDATE clipboard_items SET tags_json = ?, updated_at = strftime('%s', 'now') WHERE id = ?",
+            params![serde_json::to_string(&tags)?, id],
+        )?;
+        
+        tx.execute("DELETE FROM item_tags WHERE item_id = ?", params![id])?;
+        for tag in &tags {
+            tx.execute("INSERT INTO item_tags (item_id, tag) VALUES (?, ?)", params![id, tag])?;
+        }
+        
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClipboardItem, DuplicatePolicy};
+    
+    // 保存带有指定标签的条目
+    fn tagged(database: &Database, tags: &[&str]) -> Uuid {
+        let item = ClipboardItem {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..ClipboardItem::test_text(&format!("item {}", tags.join(" ")))
+        };
+        let id = item.id;
+        database.save_item(item, DuplicatePolicy::KeepBoth, 0).unwrap();
+        id
+    }
+    
+    fn tags_of(database: &Database, id: Uuid) -> Vec<String> {
+        database.get_item(id).unwrap().unwrap().tags
+    }
+    
+    fn counts(database: &Database) -> Vec<(String, u32, u32)> {
+        database.get_tags().unwrap()
+            .into_iter()
+            .map(|info| (info.name, info.count, info.total_count))
+            .collect()
+    }
+    
+    #[test]
+    fn tags_are_normalized_per_level() {
+        assert_eq!(normalize_tag(" work / projectA /").as_deref(), Some("work/projectA"));
+        assert_eq!(normalize_tag(" / "), None);
+        
+        assert!(is_tag_or_descendant("work", "work"));
+        assert!(is_tag_or_descendant("work/a", "work"));
+        assert!(!is_tag_or_descendant("workshop", "work"));
+    }
+    
+    #[test]
+    fn hierarchy_counts_include_children_once() {
+        let database = Database::new(":memory:").unwrap();
+        tagged(&database, &["work/a", "work/b"]);
+        tagged(&database, &["work"]);
+        tagged(&database, &["work/a/x", "personal"]);
+        
+        assert_eq!(counts(&database), [
+            ("personal".to_string(), 1, 1),
+            ("work".to_string(), 1, 3),
+            ("work/a".to_string(), 1, 2),
+            ("work/a/x".to_string(), 1, 1),
+            ("work/b".to_string(), 1, 1),
+        ]);
+    }
+    
+    #[test]
+    fn rename_moves_children_and_merges_with_existing() {
+        let database = Database::new(":memory:").unwrap();
+        let first = tagged(&database, &["work", "work/a"]);
+        let second = tagged(&database, &["job/a", "workshop"]);
+        
+        assert_eq!(database.rename_tag("work", "job").unwrap(), 1);
+        assert_eq!(tags_of(&database, first), ["job", "job/a"]);
+        assert_eq!(tags_of(&database, second), ["job/a", "workshop"]);
+        assert_eq!(counts(&database)[1], ("job/a".to_string(), 2, 2));
+        
+        // 重命名后全文索引使用新标签
+        let query = crate::SearchQuery { text: Some("job".to_string()), ..Default::default() };
+        assert_eq!(database.search_items(query, &crate::SearchSettings::default()).unwrap().len(), 2);
+        
+        assert!(database.rename_tag("job", "job/sub").is_err());
+        assert!(database.rename_tag("job", " ").is_err());
+    }
+    
+    #[test]
+    fn merge_counts_each_item_once() {
+        let database = Database::new(":memory:").unwrap();
+        let both = tagged(&database, &["draft", "wip", "other"]);
+        let one = tagged(&database, &["wip"]);
+        tagged(&database, &["other"]);
+        
+        let sources = ["draft".to_string(), "wip".to_string()];
+        assert_eq!(database.merge_tags(&sources, "todo").unwrap(), 2);
+        assert_eq!(tags_of(&database, both), ["todo", "other"]);
+        assert_eq!(tags_of(&database, one), ["todo"]);
+        assert_eq!(database.merge_tags(&sources, "todo").unwrap(), 0);
+    }
+}